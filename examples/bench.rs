@@ -10,8 +10,11 @@ async fn main() -> web3::Result {
     let http = web3::transports::Http::new("http://localhost:8545/")?;
     bench("http", http, requests);
 
-    let ipc = web3::transports::WebSocket::new("./jsonrpc.ipc").await?;
-    bench(" ipc", ipc, requests);
+    #[cfg(all(unix, feature = "ipc-tokio"))]
+    {
+        let ipc = web3::transports::Ipc::new("./jsonrpc.ipc").await?;
+        bench(" ipc", ipc, requests);
+    }
 
     Ok(())
 }
@@ -2,18 +2,20 @@
 
 use crate::api::{Namespace, Web3};
 use crate::error;
+use crate::fees::{self, FeeEstimator};
 use crate::helpers::CallFuture;
 use crate::signing::{self, Signature};
 use crate::types::{
-    Address, Bytes, Recovery, RecoveryMessage, SignedData, SignedTransaction, TransactionParameters, H256, U256,
+    AccessList, AccessListItem, Address, Bytes, DecodedTransaction, Recovery, RecoveryMessage, SignedData,
+    SignedTransaction, TransactionParameters, H256, U256,
 };
 use crate::Transport;
 use futures::future::{self, Either, Join3};
 use futures::{
     task::{Context, Poll},
-    Future, FutureExt,
+    Future, FutureExt, TryFutureExt,
 };
-use rlp::RlpStream;
+use rlp::{DecoderError, Rlp, RlpStream};
 use std::convert::TryInto;
 use std::mem;
 use std::pin::Pin;
@@ -48,6 +50,45 @@ impl<T: Transport> Accounts<T> {
         SignTransactionFuture::new(self, tx, key)
     }
 
+    /// Signs an Ethereum transaction with a given private key, filling its
+    /// missing EIP-1559 fees from the recent fee history with the given
+    /// estimator.
+    ///
+    /// The transaction is signed as an EIP-1559 transaction unless it sets a
+    /// `gas_price` or another explicit `transaction_type`, in which case this
+    /// is equivalent to `sign_transaction`.
+    pub fn sign_transaction_with_estimator<K, E>(
+        &self,
+        mut tx: TransactionParameters,
+        key: K,
+        estimator: E,
+    ) -> impl Future<Output = error::Result<SignedTransaction>>
+    where
+        K: signing::Key,
+        E: FeeEstimator,
+    {
+        let is_eip1559 = match tx.transaction_type {
+            Some(transaction_type) => transaction_type.low_u64() == EIP1559_TX_ID,
+            None => tx.gas_price.is_none(),
+        };
+        if is_eip1559 && tx.max_fee_per_gas.is_none() {
+            // Like `sign_transaction`, use the gas price as the max fee.
+            tx.max_fee_per_gas = tx.gas_price;
+        }
+        let fees = if is_eip1559 && (tx.max_fee_per_gas.is_none() || tx.max_priority_fee_per_gas.is_none()) {
+            tx.transaction_type = Some(EIP1559_TX_ID.into());
+            Either::Right(fees::estimate_fees(self.transport.clone(), estimator).map_ok(Some))
+        } else {
+            Either::Left(future::ok(None))
+        };
+
+        let accounts = self.clone();
+        fees.and_then(move |fees| match fees.map_or(Ok(()), |fees| fill_fees(&mut tx, fees)) {
+            Ok(()) => Either::Right(accounts.sign_transaction(tx, key)),
+            Err(err) => Either::Left(future::err(err)),
+        })
+    }
+
     /// Hash a message according to EIP-191.
     ///
     /// The data is a UTF-8 encoded string and will enveloped as follows:
@@ -127,6 +168,34 @@ impl<T: Transport> Accounts<T> {
         let address = signing::recover(message_hash.as_bytes(), &signature, recovery_id)?;
         Ok(address)
     }
+
+    /// Decodes a raw signed transaction and recovers its sender.
+    ///
+    /// Both legacy transactions, with or without chain replay protection, and
+    /// EIP-2930 and EIP-1559 typed transaction envelopes are supported.
+    /// Signatures with a high S value, invalid since EIP-2, are rejected.
+    pub fn decode_transaction<B>(&self, raw_transaction: B) -> error::Result<DecodedTransaction>
+    where
+        B: AsRef<[u8]>,
+    {
+        let raw_transaction = raw_transaction.as_ref();
+        let (transaction, signature, message) = decode_signed(raw_transaction)
+            .map_err(|err| error::Error::Decoder(format!("Invalid raw transaction: {:?}", err)))?;
+
+        let message_hash: H256 = signing::keccak256(&message).into();
+        let recovery = Recovery::new(message_hash, signature.v, signature.r, signature.s);
+        let from = self.recover(recovery)?;
+
+        Ok(DecodedTransaction {
+            transaction,
+            from,
+            message_hash,
+            v: signature.v,
+            r: signature.r,
+            s: signature.s,
+            transaction_hash: signing::keccak256(raw_transaction).into(),
+        })
+    }
 }
 
 type MaybeReady<T, R> = Either<future::Ready<error::Result<R>>, CallFuture<R, <T as Transport>::Out>>;
@@ -136,9 +205,9 @@ type TxParams<T> = Join3<MaybeReady<T, U256>, MaybeReady<T, U256>, MaybeReady<T,
 /// Future resolving when transaction signing is complete.
 ///
 /// Transaction signing can perform RPC requests in order to fill missing
-/// parameters required for signing `nonce`, `gas_price` and `chain_id`. Note
-/// that if all transaction parameters were provided, this future will resolve
-/// immediately.
+/// parameters required for signing `nonce`, `gas_price` (or `max_fee_per_gas`)
+/// and `chain_id`. Note that if all transaction parameters were provided, this
+/// future will resolve immediately.
 pub struct SignTransactionFuture<T: Transport, K> {
     tx: TransactionParameters,
     key: Option<K>,
@@ -148,19 +217,28 @@ pub struct SignTransactionFuture<T: Transport, K> {
 impl<T: Transport, K: signing::Key> SignTransactionFuture<T, K> {
     /// Creates a new SignTransactionFuture with accounts and transaction data.
     pub fn new(accounts: &Accounts<T>, tx: TransactionParameters, key: K) -> SignTransactionFuture<T, K> {
+        // Unsupported transactions are rejected when polled, without making any requests.
+        let supported = transaction_type(&tx) <= EIP1559_TX_ID;
         macro_rules! maybe {
             ($o: expr, $f: expr) => {
                 match $o {
                     Some(ref value) => Either::Left(future::ok(value.clone())),
+                    None if !supported => Either::Left(future::ok(Default::default())),
                     None => Either::Right($f),
                 }
             };
         }
 
+        // EIP-1559 transactions use the gas price (or the estimated one) as the default max fee instead.
+        let gas_price = match transaction_type(&tx) {
+            EIP1559_TX_ID => tx.max_fee_per_gas.or(tx.gas_price),
+            _ => tx.gas_price,
+        };
+
         let from = key.address();
         let inner = future::join3(
             maybe!(tx.nonce, accounts.web3().eth().transaction_count(from, None)),
-            maybe!(tx.gas_price, accounts.web3().eth().gas_price()),
+            maybe!(gas_price, accounts.web3().eth().gas_price()),
             maybe!(tx.chain_id.map(U256::from), accounts.web3().eth().chain_id()),
         );
 
@@ -176,17 +254,29 @@ impl<T: Transport, K: signing::Key> Future for SignTransactionFuture<T, K> {
     type Output = error::Result<SignedTransaction>;
 
     fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let transaction_type = transaction_type(&self.tx);
+        if transaction_type > EIP1559_TX_ID {
+            return Poll::Ready(Err(error::Error::InvalidInput(format!(
+                "Unsupported transaction type: {}",
+                transaction_type
+            ))));
+        }
+
         let (nonce, gas_price, chain_id) = ready!(self.inner.poll_unpin(ctx));
         let chain_id = chain_id?.as_u64();
+        let gas_price = gas_price?;
 
         let data = mem::replace(&mut self.tx.data, Bytes::default());
         let tx = Transaction {
             to: self.tx.to,
             nonce: nonce?,
             gas: self.tx.gas,
-            gas_price: gas_price?,
+            gas_price,
             value: self.tx.value,
             data: data.0,
+            transaction_type,
+            access_list: self.tx.access_list.take().unwrap_or_default(),
+            max_priority_fee_per_gas: self.tx.max_priority_fee_per_gas.unwrap_or(gas_price),
         };
         let signed = tx.sign(
             self.key
@@ -199,22 +289,73 @@ impl<T: Transport, K: signing::Key> Future for SignTransactionFuture<T, K> {
     }
 }
 
+/// Legacy (pre EIP-2718) transaction type.
+const LEGACY_TX_ID: u64 = 0;
+/// EIP-2930 access list transaction type.
+const ACCESSLISTS_TX_ID: u64 = 1;
+/// EIP-1559 dynamic fee transaction type.
+const EIP1559_TX_ID: u64 = 2;
+/// Half the order of the secp256k1 curve, the largest S value allowed by EIP-2.
+const SECP256K1_HALF_N: U256 = U256([
+    0xdfe9_2f46_681b_20a0,
+    0x5d57_6e73_57a4_501d,
+    0xffff_ffff_ffff_ffff,
+    0x7fff_ffff_ffff_ffff,
+]);
+
+/// Returns the EIP-2718 type of the transaction, inferring it from the
+/// provided fields when it is not set explicitly.
+fn transaction_type(tx: &TransactionParameters) -> u64 {
+    match tx.transaction_type {
+        Some(transaction_type) => transaction_type.low_u64(),
+        None if tx.max_fee_per_gas.is_some() || tx.max_priority_fee_per_gas.is_some() => EIP1559_TX_ID,
+        None if tx.access_list.is_some() => ACCESSLISTS_TX_ID,
+        None => LEGACY_TX_ID,
+    }
+}
+
+/// Fills the EIP-1559 fees missing from the transaction with the estimated ones.
+fn fill_fees(tx: &mut TransactionParameters, fees: fees::Fees) -> error::Result<()> {
+    match (tx.max_fee_per_gas, tx.max_priority_fee_per_gas) {
+        // The priority fee can never exceed the max fee set by the caller.
+        (Some(max_fee_per_gas), None) => {
+            tx.max_priority_fee_per_gas = Some(fees.max_priority_fee_per_gas.min(max_fee_per_gas));
+        }
+        // Keep the estimated base fee headroom on top of an explicitly set priority fee.
+        (None, Some(max_priority_fee_per_gas)) => {
+            let max_fee_per_gas = fees
+                .max_fee_per_gas
+                .saturating_sub(fees.max_priority_fee_per_gas)
+                .checked_add(max_priority_fee_per_gas)
+                .ok_or_else(|| error::Error::InvalidInput("Max priority fee per gas is too large".into()))?;
+            tx.max_fee_per_gas = Some(max_fee_per_gas);
+        }
+        (None, None) => {
+            tx.max_fee_per_gas = Some(fees.max_fee_per_gas);
+            tx.max_priority_fee_per_gas = Some(fees.max_priority_fee_per_gas);
+        }
+        (Some(_), Some(_)) => {}
+    }
+    Ok(())
+}
+
 /// A transaction used for RLP encoding, hashing and signing.
 struct Transaction {
     to: Option<Address>,
     nonce: U256,
     gas: U256,
+    /// Gas price, or the max fee per gas for EIP-1559 transactions.
     gas_price: U256,
     value: U256,
     data: Vec<u8>,
+    transaction_type: u64,
+    access_list: AccessList,
+    max_priority_fee_per_gas: U256,
 }
 
 impl Transaction {
-    /// RLP encode an unsigned transaction for the specified chain ID.
-    fn rlp_append_unsigned(&self, rlp: &mut RlpStream, chain_id: u64) {
-        rlp.begin_list(9);
-        rlp.append(&self.nonce);
-        rlp.append(&self.gas_price);
+    /// RLP encode the fields shared by all transaction types.
+    fn rlp_append_fields(&self, rlp: &mut RlpStream) {
         rlp.append(&self.gas);
         if let Some(to) = self.to {
             rlp.append(&to);
@@ -223,62 +364,222 @@ impl Transaction {
         }
         rlp.append(&self.value);
         rlp.append(&self.data);
-        rlp.append(&chain_id);
-        rlp.append(&0u8);
-        rlp.append(&0u8);
     }
 
-    /// RLP encode a signed transaction with the specified signature.
-    fn rlp_append_signed(&self, rlp: &mut RlpStream, signature: &Signature) {
+    /// Encode a legacy transaction, unsigned for the specified chain ID when no
+    /// signature is given.
+    fn encode_legacy(&self, chain_id: u64, signature: Option<&Signature>) -> Vec<u8> {
+        let mut rlp = RlpStream::new();
         rlp.begin_list(9);
         rlp.append(&self.nonce);
         rlp.append(&self.gas_price);
-        rlp.append(&self.gas);
-        if let Some(to) = self.to {
-            rlp.append(&to);
+        self.rlp_append_fields(&mut rlp);
+        if let Some(signature) = signature {
+            rlp_append_signature(&mut rlp, signature);
         } else {
-            rlp.append(&"");
+            rlp.append(&chain_id);
+            rlp.append(&0u8);
+            rlp.append(&0u8);
         }
-        rlp.append(&self.value);
-        rlp.append(&self.data);
-        rlp.append(&signature.v);
-        rlp.append(&U256::from_big_endian(signature.r.as_bytes()));
-        rlp.append(&U256::from_big_endian(signature.s.as_bytes()));
+        rlp.out()
     }
 
-    /// Sign and return a raw signed transaction.
-    fn sign(self, sign: impl signing::Key, chain_id: u64) -> SignedTransaction {
+    /// Encode an EIP-2718 typed transaction envelope, signed when a signature
+    /// is given.
+    fn encode_typed(&self, chain_id: u64, signature: Option<&Signature>) -> Vec<u8> {
+        let fields = if self.transaction_type == EIP1559_TX_ID { 9 } else { 8 };
         let mut rlp = RlpStream::new();
-        self.rlp_append_unsigned(&mut rlp, chain_id);
+        rlp.begin_list(fields + if signature.is_some() { 3 } else { 0 });
+        rlp.append(&chain_id);
+        rlp.append(&self.nonce);
+        if self.transaction_type == EIP1559_TX_ID {
+            rlp.append(&self.max_priority_fee_per_gas);
+        }
+        rlp.append(&self.gas_price);
+        self.rlp_append_fields(&mut rlp);
+        rlp.begin_list(self.access_list.len());
+        for item in &self.access_list {
+            rlp.begin_list(2);
+            rlp.append(&item.address);
+            rlp.append_list(&item.storage_keys);
+        }
+        if let Some(signature) = signature {
+            rlp_append_signature(&mut rlp, signature);
+        }
 
-        let hash = signing::keccak256(rlp.as_raw());
-        let signature = sign
-            .sign(&hash, Some(chain_id))
-            .expect("hash is non-zero 32-bytes; qed");
+        let mut encoded = vec![self.transaction_type as u8];
+        encoded.extend_from_slice(rlp.as_raw());
+        encoded
+    }
 
-        rlp.clear();
-        self.rlp_append_signed(&mut rlp, &signature);
+    fn encode(&self, chain_id: u64, signature: Option<&Signature>) -> Vec<u8> {
+        match self.transaction_type {
+            LEGACY_TX_ID => self.encode_legacy(chain_id, signature),
+            _ => self.encode_typed(chain_id, signature),
+        }
+    }
+
+    /// Sign and return a raw signed transaction.
+    fn sign(self, sign: impl signing::Key, chain_id: u64) -> SignedTransaction {
+        let hash = signing::keccak256(&self.encode(chain_id, None));
+        let signature = if self.transaction_type == LEGACY_TX_ID {
+            sign.sign(&hash, Some(chain_id))
+        } else {
+            // Typed transactions commit to the chain ID in the payload and
+            // carry the plain y-parity instead of an 'Electrum' V value.
+            sign.sign(&hash, None).map(|signature| Signature {
+                v: signature.v - 27,
+                ..signature
+            })
+        }
+        .expect("hash is non-zero 32-bytes; qed");
 
-        let transaction_hash = signing::keccak256(rlp.as_raw()).into();
-        let raw_transaction = rlp.out().into();
+        let raw_transaction = self.encode(chain_id, Some(&signature));
+        let transaction_hash = signing::keccak256(&raw_transaction).into();
 
         SignedTransaction {
             message_hash: hash.into(),
             v: signature.v,
             r: signature.r,
             s: signature.s,
-            raw_transaction,
+            raw_transaction: raw_transaction.into(),
             transaction_hash,
         }
     }
 }
 
+/// Decodes the parameters and signature of a raw signed transaction together
+/// with the message that was signed.
+fn decode_signed(raw: &[u8]) -> Result<(TransactionParameters, Signature, Vec<u8>), DecoderError> {
+    let (transaction_type, payload) = match raw.first() {
+        Some(&byte) if byte >= 0xc0 => (LEGACY_TX_ID, raw),
+        Some(&byte) if u64::from(byte) == ACCESSLISTS_TX_ID || u64::from(byte) == EIP1559_TX_ID => {
+            (u64::from(byte), &raw[1..])
+        }
+        Some(_) => return Err(DecoderError::Custom("Unsupported transaction type")),
+        None => return Err(DecoderError::RlpIsTooShort),
+    };
+
+    let rlp = Rlp::new(payload);
+    if rlp.payload_info()?.total() != payload.len() {
+        return Err(DecoderError::RlpIsTooBig);
+    }
+    let item_count = match transaction_type {
+        LEGACY_TX_ID => 9,
+        ACCESSLISTS_TX_ID => 11,
+        _ => 12,
+    };
+    if rlp.item_count()? != item_count {
+        return Err(DecoderError::RlpIncorrectListLen);
+    }
+
+    let signature_at = item_count - 3;
+    let signature = Signature {
+        v: rlp.val_at(signature_at)?,
+        r: rlp_h256_at(&rlp, signature_at + 1)?,
+        s: rlp_h256_at(&rlp, signature_at + 2)?,
+    };
+    if U256::from_big_endian(signature.s.as_bytes()) > SECP256K1_HALF_N {
+        return Err(DecoderError::Custom("Invalid signature S value"));
+    }
+
+    let mut tx = TransactionParameters {
+        transaction_type: Some(transaction_type.into()),
+        ..Default::default()
+    };
+    let mut message = RlpStream::new();
+    if transaction_type == LEGACY_TX_ID {
+        tx.nonce = Some(rlp.val_at(0)?);
+        tx.gas_price = Some(rlp.val_at(1)?);
+        decode_fields(&rlp, 2, &mut tx)?;
+        tx.chain_id = match signature.v {
+            27 | 28 => None,
+            v if v >= 35 => Some((v - 35) / 2),
+            _ => return Err(DecoderError::Custom("Invalid signature V value")),
+        };
+
+        message.begin_list(if tx.chain_id.is_some() { 9 } else { 6 });
+        for index in 0..6 {
+            message.append_raw(rlp.at(index)?.as_raw(), 1);
+        }
+        if let Some(chain_id) = tx.chain_id {
+            message.append(&chain_id);
+            message.append(&0u8);
+            message.append(&0u8);
+        }
+        return Ok((tx, signature, message.out()));
+    }
+
+    tx.chain_id = Some(rlp.val_at(0)?);
+    tx.nonce = Some(rlp.val_at(1)?);
+    let fields_at = if transaction_type == EIP1559_TX_ID {
+        tx.max_priority_fee_per_gas = Some(rlp.val_at(2)?);
+        tx.max_fee_per_gas = Some(rlp.val_at(3)?);
+        4
+    } else {
+        tx.gas_price = Some(rlp.val_at(2)?);
+        3
+    };
+    decode_fields(&rlp, fields_at, &mut tx)?;
+    let access_list = rlp
+        .at(fields_at + 4)?
+        .iter()
+        .map(|item| {
+            if item.item_count()? != 2 {
+                return Err(DecoderError::RlpIncorrectListLen);
+            }
+            Ok(AccessListItem {
+                address: item.val_at(0)?,
+                storage_keys: item.list_at(1)?,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    tx.access_list = Some(access_list);
+    if signature.v > 1 {
+        return Err(DecoderError::Custom("Invalid signature Y parity"));
+    }
+
+    message.begin_list(signature_at);
+    for index in 0..signature_at {
+        message.append_raw(rlp.at(index)?.as_raw(), 1);
+    }
+    let mut typed_message = vec![transaction_type as u8];
+    typed_message.extend_from_slice(message.as_raw());
+    Ok((tx, signature, typed_message))
+}
+
+/// Decodes the `gas`, `to`, `value` and `data` fields shared by all
+/// transaction types, starting at the given index.
+fn decode_fields(rlp: &Rlp, at: usize, tx: &mut TransactionParameters) -> Result<(), DecoderError> {
+    tx.gas = rlp.val_at(at)?;
+    let to = rlp.at(at + 1)?;
+    tx.to = if to.is_empty() { None } else { Some(to.as_val()?) };
+    tx.value = rlp.val_at(at + 2)?;
+    tx.data = Bytes(rlp.val_at(at + 3)?);
+    Ok(())
+}
+
+/// Decodes a signature scalar, which is encoded without leading zeros.
+fn rlp_h256_at(rlp: &Rlp, index: usize) -> Result<H256, DecoderError> {
+    let mut bytes = [0u8; 32];
+    rlp.val_at::<U256>(index)?.to_big_endian(&mut bytes);
+    Ok(bytes.into())
+}
+
+/// RLP encode the signature of a signed transaction.
+fn rlp_append_signature(rlp: &mut RlpStream, signature: &Signature) {
+    rlp.append(&signature.v);
+    rlp.append(&U256::from_big_endian(signature.r.as_bytes()));
+    rlp.append(&U256::from_big_endian(signature.s.as_bytes()));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fees::FeeStrategy;
     use crate::helpers::tests::TestTransport;
     use crate::signing::{SecretKey, SecretKeyRef};
-    use crate::types::Bytes;
+    use crate::types::{AccessListItem, Bytes};
     use rustc_hex::FromHex;
     use serde_json::json;
 
@@ -365,6 +666,306 @@ mod tests {
         accounts.transport().assert_no_more_requests();
     }
 
+    #[test]
+    fn accounts_sign_access_list_transaction() {
+        let tx = TransactionParameters {
+            nonce: Some(0.into()),
+            to: Some("F0109fC8DF283027b6285cc889F5aA624EaC1F55".parse().unwrap()),
+            gas: 2_000_000.into(),
+            gas_price: Some(21_000_000_000u64.into()),
+            value: 1_000_000_000.into(),
+            chain_id: Some(1),
+            access_list: Some(vec![AccessListItem {
+                address: "de0b295669a9fd93d5f28d9ec85e40f4cb697bae".parse().unwrap(),
+                storage_keys: vec![H256::from_low_u64_be(1), H256::from_low_u64_be(7)],
+            }]),
+            ..Default::default()
+        };
+        let key: SecretKey = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse()
+            .unwrap();
+
+        let accounts = Accounts::new(TestTransport::default());
+        let signed = futures::executor::block_on(accounts.sign_transaction(tx, &key));
+
+        // sign_transaction makes no requests when all parameters are specified
+        accounts.transport().assert_no_more_requests();
+
+        let expected = SignedTransaction {
+            message_hash: "881a1da978f1a91a509069f30084d3e14a038ab6008a6d0c0549abae4d7db280"
+                .parse()
+                .unwrap(),
+            v: 0,
+            r: "e546579eba505e5be8fb7836c079180c2ee68b4b6f495a79b520f5de5f9c6d95"
+                .parse()
+                .unwrap(),
+            s: "4feea5dbf03c4940650427e0f5d85521fad76c24ffceaed2a3ff45d9083086fb"
+                .parse()
+                .unwrap(),
+            raw_transaction: Bytes(
+                "01f8c701808504e3b29200831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca0080f85bf85994de0b295669a9fd93d5f28d9ec85e40f4cb697baef842a00000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000780a0e546579eba505e5be8fb7836c079180c2ee68b4b6f495a79b520f5de5f9c6d95a04feea5dbf03c4940650427e0f5d85521fad76c24ffceaed2a3ff45d9083086fb"
+                    .from_hex()
+                    .unwrap(),
+            ),
+            transaction_hash: "c996feb9b2ef22108d207939f1ac85353562d53f3145b5748f8e91e4503f3bd7"
+                .parse()
+                .unwrap(),
+        };
+
+        assert_eq!(signed, Ok(expected));
+    }
+
+    #[test]
+    fn accounts_sign_eip1559_transaction() {
+        let tx = TransactionParameters {
+            nonce: Some(1.into()),
+            to: Some("F0109fC8DF283027b6285cc889F5aA624EaC1F55".parse().unwrap()),
+            gas: 2_000_000.into(),
+            value: 1_000_000_000.into(),
+            data: Bytes(vec![1, 2, 3, 4]),
+            chain_id: Some(1),
+            max_priority_fee_per_gas: Some(2_000_000_000u64.into()),
+            ..Default::default()
+        };
+        let key: SecretKey = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse()
+            .unwrap();
+
+        let mut transport = TestTransport::default();
+        transport.add_response(json!(U256::from(21_000_000_000u64)));
+
+        let signed = {
+            let accounts = Accounts::new(&transport);
+            futures::executor::block_on(accounts.sign_transaction(tx, &key)).unwrap()
+        };
+
+        // the max fee per gas defaults to the current gas price
+        transport.assert_request("eth_gasPrice", &[]);
+        transport.assert_no_more_requests();
+
+        let expected = SignedTransaction {
+            message_hash: "9fac09bd3dac2e0e6941eaddefcdf1b76a7ba0a5c9be2d1386873f0a9798d464"
+                .parse()
+                .unwrap(),
+            v: 1,
+            r: "91fb41ea1f755450e148022f305a08fb33820c9608bfa4ab01a7dc6c631895d4"
+                .parse()
+                .unwrap(),
+            s: "1d2fe0a8e7859b466cb498d3f9056c1331e164e91ccff9dd3e2cc358aa76f4a6"
+                .parse()
+                .unwrap(),
+            raw_transaction: Bytes(
+                "02f874010184773594008504e3b29200831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008401020304c001a091fb41ea1f755450e148022f305a08fb33820c9608bfa4ab01a7dc6c631895d4a01d2fe0a8e7859b466cb498d3f9056c1331e164e91ccff9dd3e2cc358aa76f4a6"
+                    .from_hex()
+                    .unwrap(),
+            ),
+            transaction_hash: "6c047dd805d967fd21e5a811425b2509216254833ea823aaa05c43a188ff24d2"
+                .parse()
+                .unwrap(),
+        };
+        assert_eq!(signed, expected);
+
+        let recovered = Accounts::new(&transport).recover(&signed).unwrap();
+        assert_eq!(recovered, signing::secret_key_address(&key));
+    }
+
+    #[test]
+    fn accounts_sign_transaction_with_estimator() {
+        let key: SecretKey = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+            .parse()
+            .unwrap();
+        let tx = TransactionParameters {
+            nonce: Some(0.into()),
+            chain_id: Some(42),
+            max_priority_fee_per_gas: Some(7.into()),
+            ..Default::default()
+        };
+
+        let mut transport = TestTransport::default();
+        transport.add_response(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x64", "0x64"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x5"]]
+        }));
+
+        let signed = {
+            let accounts = Accounts::new(&transport);
+            futures::executor::block_on(accounts.sign_transaction_with_estimator(tx.clone(), &key, FeeStrategy::Medium))
+                .unwrap()
+        };
+
+        transport.assert_request(
+            "eth_feeHistory",
+            &[
+                json!("0xa").to_string(),
+                json!("latest").to_string(),
+                json!([50.0]).to_string(),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        // the explicit priority fee is kept and the max fee leaves room for a doubled base fee
+        let expected = futures::executor::block_on(Accounts::new(TestTransport::default()).sign_transaction(
+            TransactionParameters {
+                max_fee_per_gas: Some(207.into()),
+                ..tx
+            },
+            &key,
+        ))
+        .unwrap();
+        assert_eq!(signed, expected);
+    }
+
+    #[test]
+    fn accounts_sign_transaction_with_estimator_clamps_priority_fee() {
+        let key: SecretKey = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+            .parse()
+            .unwrap();
+        let tx = TransactionParameters {
+            nonce: Some(0.into()),
+            chain_id: Some(42),
+            max_fee_per_gas: Some(3.into()),
+            ..Default::default()
+        };
+
+        let mut transport = TestTransport::default();
+        transport.add_response(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x1", "0x1"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x5"]]
+        }));
+
+        let signed = {
+            let accounts = Accounts::new(&transport);
+            futures::executor::block_on(accounts.sign_transaction_with_estimator(tx.clone(), &key, FeeStrategy::Medium))
+                .unwrap()
+        };
+
+        // the estimated priority fee is capped by the explicit max fee
+        let expected = futures::executor::block_on(Accounts::new(TestTransport::default()).sign_transaction(
+            TransactionParameters {
+                max_priority_fee_per_gas: Some(3.into()),
+                ..tx
+            },
+            &key,
+        ))
+        .unwrap();
+        assert_eq!(signed, expected);
+    }
+
+    #[test]
+    fn accounts_sign_transaction_with_estimator_rejects_overflowing_fees() {
+        let key: SecretKey = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+            .parse()
+            .unwrap();
+
+        let mut transport = TestTransport::default();
+        transport.add_response(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x64", "0x64"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x5"]]
+        }));
+
+        let accounts = Accounts::new(&transport);
+        let signed = futures::executor::block_on(accounts.sign_transaction_with_estimator(
+            TransactionParameters {
+                nonce: Some(0.into()),
+                chain_id: Some(42),
+                max_priority_fee_per_gas: Some(U256::MAX),
+                ..Default::default()
+            },
+            &key,
+            FeeStrategy::Medium,
+        ));
+
+        assert_eq!(
+            signed,
+            Err(error::Error::InvalidInput(
+                "Max priority fee per gas is too large".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn accounts_sign_legacy_transaction_with_estimator() {
+        let key: SecretKey = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+            .parse()
+            .unwrap();
+
+        let accounts = Accounts::new(TestTransport::default());
+        let signed = futures::executor::block_on(accounts.sign_transaction_with_estimator(
+            TransactionParameters {
+                nonce: Some(0.into()),
+                gas_price: Some(1.into()),
+                chain_id: Some(42),
+                ..Default::default()
+            },
+            &key,
+            FeeStrategy::Medium,
+        ))
+        .unwrap();
+
+        // transactions with a gas price are signed as legacy transactions without estimating fees
+        accounts.transport().assert_no_more_requests();
+        assert!(signed.raw_transaction.0[0] >= 0xc0);
+    }
+
+    #[test]
+    fn accounts_sign_eip1559_transaction_with_gas_price() {
+        let key: SecretKey = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+            .parse()
+            .unwrap();
+        let tx = TransactionParameters {
+            nonce: Some(0.into()),
+            chain_id: Some(42),
+            transaction_type: Some(2.into()),
+            gas_price: Some(5.into()),
+            ..Default::default()
+        };
+
+        let accounts = Accounts::new(TestTransport::default());
+        let signed = futures::executor::block_on(accounts.sign_transaction(tx.clone(), &key)).unwrap();
+
+        // the gas price is used as the max fee instead of requesting the current one
+        accounts.transport().assert_no_more_requests();
+        let expected = futures::executor::block_on(accounts.sign_transaction(
+            TransactionParameters {
+                gas_price: None,
+                max_fee_per_gas: Some(5.into()),
+                ..tx
+            },
+            &key,
+        ))
+        .unwrap();
+        assert_eq!(signed, expected);
+    }
+
+    #[test]
+    fn accounts_sign_transaction_with_unsupported_type() {
+        let key: SecretKey = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+            .parse()
+            .unwrap();
+
+        let accounts = Accounts::new(TestTransport::default());
+        let signed = futures::executor::block_on(accounts.sign_transaction(
+            TransactionParameters {
+                transaction_type: Some(3.into()),
+                ..Default::default()
+            },
+            &key,
+        ));
+
+        assert_eq!(
+            signed,
+            Err(error::Error::InvalidInput("Unsupported transaction type: 3".into()))
+        );
+        // missing parameters are not requested for unsupported transactions.
+        accounts.transport().assert_no_more_requests();
+    }
+
     #[test]
     fn accounts_hash_message() {
         // test vector taken from:
@@ -468,6 +1069,151 @@ mod tests {
         accounts.transport().assert_no_more_requests();
     }
 
+    /// Decodes a raw transaction signed by the web3js test key and checks that
+    /// signing the decoded parameters again yields the same raw transaction.
+    fn decode_and_sign_again(raw: &str) -> DecodedTransaction {
+        let key: SecretKey = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse()
+            .unwrap();
+        let raw: Vec<u8> = raw.from_hex().unwrap();
+
+        let accounts = Accounts::new(TestTransport::default());
+        let decoded = accounts.decode_transaction(&raw).unwrap();
+        assert_eq!(decoded.from, signing::secret_key_address(&key));
+
+        let signed = futures::executor::block_on(accounts.sign_transaction(decoded.transaction.clone(), &key)).unwrap();
+        assert_eq!(signed.raw_transaction.0, raw);
+        assert_eq!(signed.message_hash, decoded.message_hash);
+        assert_eq!(signed.transaction_hash, decoded.transaction_hash);
+        assert_eq!((signed.v, signed.r, signed.s), (decoded.v, decoded.r, decoded.s));
+
+        // decoding makes no requests
+        accounts.transport().assert_no_more_requests();
+        decoded
+    }
+
+    #[test]
+    fn accounts_decode_legacy_transaction() {
+        let decoded = decode_and_sign_again("f869808504e3b29200831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a0c9cf86333bcb065d140032ecaab5d9281bde80f21b9687b3e94161de42d51895a0727a108a0b8d101465414033c3f705a9c7b826e596766046ee1183dbc8aeaa68");
+
+        assert_eq!(
+            decoded.transaction,
+            TransactionParameters {
+                nonce: Some(0.into()),
+                to: Some("F0109fC8DF283027b6285cc889F5aA624EaC1F55".parse().unwrap()),
+                gas: 2_000_000.into(),
+                gas_price: Some(21_000_000_000u64.into()),
+                value: 1_000_000_000.into(),
+                chain_id: Some(1),
+                transaction_type: Some(0.into()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            decoded.transaction_hash,
+            "de8db924885b0803d2edc335f745b2b8750c8848744905684c20b987443a9593"
+                .parse()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn accounts_decode_access_list_transaction() {
+        let decoded = decode_and_sign_again("01f8c701808504e3b29200831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca0080f85bf85994de0b295669a9fd93d5f28d9ec85e40f4cb697baef842a00000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000780a0e546579eba505e5be8fb7836c079180c2ee68b4b6f495a79b520f5de5f9c6d95a04feea5dbf03c4940650427e0f5d85521fad76c24ffceaed2a3ff45d9083086fb");
+
+        assert_eq!(decoded.transaction.transaction_type, Some(1.into()));
+        assert_eq!(decoded.transaction.chain_id, Some(1));
+        assert_eq!(decoded.transaction.gas_price, Some(21_000_000_000u64.into()));
+        assert_eq!(
+            decoded.transaction.access_list,
+            Some(vec![AccessListItem {
+                address: "de0b295669a9fd93d5f28d9ec85e40f4cb697bae".parse().unwrap(),
+                storage_keys: vec![H256::from_low_u64_be(1), H256::from_low_u64_be(7)],
+            }])
+        );
+    }
+
+    #[test]
+    fn accounts_decode_eip1559_transaction() {
+        let decoded = decode_and_sign_again("02f874010184773594008504e3b29200831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008401020304c001a091fb41ea1f755450e148022f305a08fb33820c9608bfa4ab01a7dc6c631895d4a01d2fe0a8e7859b466cb498d3f9056c1331e164e91ccff9dd3e2cc358aa76f4a6");
+
+        assert_eq!(
+            decoded.transaction,
+            TransactionParameters {
+                nonce: Some(1.into()),
+                to: Some("F0109fC8DF283027b6285cc889F5aA624EaC1F55".parse().unwrap()),
+                gas: 2_000_000.into(),
+                value: 1_000_000_000.into(),
+                data: Bytes(vec![1, 2, 3, 4]),
+                chain_id: Some(1),
+                transaction_type: Some(2.into()),
+                access_list: Some(vec![]),
+                max_fee_per_gas: Some(21_000_000_000u64.into()),
+                max_priority_fee_per_gas: Some(2_000_000_000u64.into()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(decoded.v, 1);
+    }
+
+    #[test]
+    fn accounts_decode_transaction_without_replay_protection() {
+        let raw: Vec<u8> = "f855058504a817c80082520880808560806040521ca04cc5c75a7341d863674bc2805bd454dc01d0ff4675241b8bc58a84075ef874cda0315a9408d5eb1043773b4d9eeb6ebab590b4bd3a160f2bd2f69ac3f5e410868a"
+            .from_hex()
+            .unwrap();
+
+        let accounts = Accounts::new(TestTransport::default());
+        let decoded = accounts.decode_transaction(&raw).unwrap();
+
+        assert_eq!(
+            decoded.from,
+            "2c7536E3605D9C16a7a3D7b1898e529396a65c23".parse().unwrap()
+        );
+        assert_eq!(decoded.transaction.chain_id, None);
+        assert_eq!(decoded.transaction.to, None);
+        assert_eq!(decoded.transaction.data, Bytes(vec![0x60, 0x80, 0x60, 0x40, 0x52]));
+        assert_eq!(decoded.v, 28);
+        assert_eq!(
+            decoded.message_hash,
+            "8531f02398c0bb6812c44a65533424023f38a789823c03f5dfbec55a272eb30b"
+                .parse()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn accounts_decode_invalid_transaction() {
+        let accounts = Accounts::new(TestTransport::default());
+        let raw: Vec<u8> = "f869808504e3b29200831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008025a0c9cf86333bcb065d140032ecaab5d9281bde80f21b9687b3e94161de42d51895a0727a108a0b8d101465414033c3f705a9c7b826e596766046ee1183dbc8aeaa68"
+            .from_hex()
+            .unwrap();
+
+        // trailing bytes
+        let mut trailing = raw.clone();
+        trailing.push(0);
+        assert!(accounts.decode_transaction(&trailing).is_err());
+        // unsupported transaction type
+        let mut typed = vec![0x03];
+        typed.extend_from_slice(&raw);
+        assert!(accounts.decode_transaction(&typed).is_err());
+        // legacy fields in a typed envelope
+        typed[0] = 0x02;
+        assert!(accounts.decode_transaction(&typed).is_err());
+        // empty input
+        assert!(accounts.decode_transaction(&[]).is_err());
+        // the EIP-1559 test transaction with a malleated high S value, which is
+        // invalid since EIP-2 but still recovers the same sender
+        let high_s: Vec<u8> = "02f874010184773594008504e3b29200831e848094f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008401020304c080a091fb41ea1f755450e148022f305a08fb33820c9608bfa4ab01a7dc6c631895d4a0e2d01f57187a64b9934b672c06fa93eb88cd77fd9278a65e81a59b3425bf4c9b"
+            .from_hex()
+            .unwrap();
+        assert_eq!(
+            accounts.decode_transaction(&high_s),
+            Err(error::Error::Decoder(
+                "Invalid raw transaction: Custom(\"Invalid signature S value\")".into()
+            ))
+        );
+    }
+
     #[test]
     fn sign_transaction_data() {
         // retrieved test vector from:
@@ -480,6 +1226,9 @@ mod tests {
             to: Some("F0109fC8DF283027b6285cc889F5aA624EaC1F55".parse().unwrap()),
             value: 1_000_000_000.into(),
             data: Vec::new(),
+            transaction_type: LEGACY_TX_ID,
+            access_list: Vec::new(),
+            max_priority_fee_per_gas: 0.into(),
         };
         let skey: SecretKey = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
             .parse()
@@ -3,7 +3,7 @@
 use crate::api::Namespace;
 use crate::helpers::{self, CallFuture};
 use crate::types::{
-    Address, Block, BlockId, BlockNumber, Bytes, CallRequest, Filter, Index, Log, SyncState, Transaction,
+    Address, Block, BlockId, BlockNumber, Bytes, CallRequest, FeeHistory, Filter, Index, Log, SyncState, Transaction,
     TransactionId, TransactionReceipt, TransactionRequest, Work, H256, H520, H64, U256, U64,
 };
 use crate::Transport;
@@ -86,6 +86,29 @@ impl<T: Transport> Eth<T> {
         CallFuture::new(self.transport.execute("eth_gasPrice", vec![]))
     }
 
+    /// Get the fee history of the `block_count` blocks up to `newest_block`,
+    /// with the priority fees paid at the given `reward_percentiles`.
+    pub fn fee_history(
+        &self,
+        block_count: U256,
+        newest_block: BlockNumber,
+        reward_percentiles: Option<Vec<f64>>,
+    ) -> CallFuture<FeeHistory, T::Out> {
+        let block_count = helpers::serialize(&block_count);
+        let newest_block = helpers::serialize(&newest_block);
+        let reward_percentiles = helpers::serialize(&reward_percentiles.unwrap_or_default());
+
+        CallFuture::new(
+            self.transport
+                .execute("eth_feeHistory", vec![block_count, newest_block, reward_percentiles]),
+        )
+    }
+
+    /// Get current recommended priority fee per gas
+    pub fn max_priority_fee_per_gas(&self) -> CallFuture<U256, T::Out> {
+        CallFuture::new(self.transport.execute("eth_maxPriorityFeePerGas", vec![]))
+    }
+
     /// Get balance of given address
     pub fn balance(&self, address: Address, block: Option<BlockNumber>) -> CallFuture<U256, T::Out> {
         let address = helpers::serialize(&address);
@@ -97,7 +120,8 @@ impl<T: Transport> Eth<T> {
     /// Get all logs matching a given filter object
     pub fn logs(&self, filter: Filter) -> CallFuture<Vec<Log>, T::Out> {
         let filter = helpers::serialize(&filter);
-        CallFuture::new(self.transport.execute("eth_getLogs", vec![filter]))
+        let (id, request) = self.transport.prepare("eth_getLogs", vec![filter]);
+        self.transport.send_typed(id, request)
     }
 
     /// Get block details with transaction hashes.
@@ -335,8 +359,8 @@ mod tests {
     use crate::api::Namespace;
     use crate::rpc::Value;
     use crate::types::{
-        Address, Block, BlockId, BlockNumber, Bytes, CallRequest, FilterBuilder, Log, SyncInfo, SyncState, Transaction,
-        TransactionId, TransactionReceipt, TransactionRequest, Work, H256, H520, H64,
+        Address, Block, BlockId, BlockNumber, Bytes, CallRequest, FeeHistory, FilterBuilder, Log, SyncInfo, SyncState,
+        Transaction, TransactionId, TransactionReceipt, TransactionRequest, Work, H256, H520, H64,
     };
 
     use super::Eth;
@@ -405,6 +429,13 @@ mod tests {
         "uncles": []
       }"#;
 
+    const EXAMPLE_FEE_HISTORY: &'static str = r#"{
+    "oldestBlock": "0xc72773",
+    "baseFeePerGas": ["0x7a3ac3a1d", "0x7b82e2d43", "0x7a6a88c0e", "0x7c9ba9ac8"],
+    "gasUsedRatio": [0.5271004, 0.4501283, 0.6004102],
+    "reward": [["0x3b9aca00", "0x59682f00"], ["0x3b9aca00", "0x77359400"], ["0x9502f900", "0xb2d05e00"]]
+  }"#;
+
     // taken from RPC docs, but with leading `00` added to `blockHash`
     // and `transactionHash` fields because RPC docs currently show
     // 31-byte values in both positions (must be 32 bytes).
@@ -464,6 +495,7 @@ mod tests {
         from: None, to: Some(Address::from_low_u64_be(0x123)),
         gas: None, gas_price: None,
         value: Some(0x1.into()), data: None,
+        ..Default::default()
       }, None
       =>
       "eth_call", vec![r#"{"to":"0x0000000000000000000000000000000000000123","value":"0x1"}"#, r#""latest""#];
@@ -495,6 +527,7 @@ mod tests {
         from: None, to: Some(Address::from_low_u64_be(0x123)),
         gas: None, gas_price: None,
         value: Some(0x1.into()), data: None,
+        ..Default::default()
       }, None
       =>
       "eth_estimateGas", vec![r#"{"to":"0x0000000000000000000000000000000000000123","value":"0x1"}"#];
@@ -506,6 +539,7 @@ mod tests {
         from: None, to: None,
         gas: None, gas_price: None,
         value: Some(0x1.into()), data: None,
+        ..Default::default()
       }, None
       =>
       "eth_estimateGas", vec![r#"{"value":"0x1"}"#];
@@ -517,6 +551,7 @@ mod tests {
         from: None, to: Some(Address::from_low_u64_be(0x123)),
         gas: None, gas_price: None,
         value: Some(0x1.into()), data: None,
+        ..Default::default()
       }, Some(0x123.into())
       =>
       "eth_estimateGas", vec![r#"{"to":"0x0000000000000000000000000000000000000123","value":"0x1"}"#, r#""0x123""#];
@@ -528,6 +563,32 @@ mod tests {
       Value::String("0x123".into()) => 0x123
     );
 
+    rpc_test! (
+      Eth:fee_history, 0x3, BlockNumber::Latest, Some(vec![10.0, 50.0])
+      =>
+      "eth_feeHistory", vec![r#""0x3""#, r#""latest""#, r#"[10.0,50.0]"#];
+      ::serde_json::from_str(EXAMPLE_FEE_HISTORY).unwrap()
+      => ::serde_json::from_str::<FeeHistory>(EXAMPLE_FEE_HISTORY).unwrap()
+    );
+
+    rpc_test! (
+      Eth:fee_history:without_percentiles, 0x1, BlockNumber::Pending, None
+      =>
+      "eth_feeHistory", vec![r#""0x1""#, r#""pending""#, r#"[]"#];
+      json!({"oldestBlock": "0x1", "baseFeePerGas": ["0x1", "0x2"], "gasUsedRatio": [0.5]})
+      => FeeHistory {
+        oldest_block: 0x1.into(),
+        base_fee_per_gas: vec![0x1.into(), 0x2.into()],
+        gas_used_ratio: vec![0.5],
+        reward: None,
+      }
+    );
+
+    rpc_test! (
+      Eth:max_priority_fee_per_gas => "eth_maxPriorityFeePerGas";
+      Value::String("0x3b9aca00".into()) => 1_000_000_000
+    );
+
     rpc_test! (
       Eth:balance, Address::from_low_u64_be(0x123), None
       =>
@@ -746,6 +807,7 @@ mod tests {
         gas: None, gas_price: Some(0x1.into()),
         value: Some(0x1.into()), data: None,
         nonce: None, condition: None,
+        ..Default::default()
       }
       =>
       "eth_sendTransaction", vec![r#"{"from":"0x0000000000000000000000000000000000000123","gasPrice":"0x1","to":"0x0000000000000000000000000000000000000123","value":"0x1"}"#];
@@ -52,6 +52,7 @@ mod tests {
                 gas_price: None,
                 value: Some(0x1.into()),
                 data: None,
+                ..Default::default()
             },
             CallRequest {
                 from: Some(Address::from_low_u64_be(0x321)),
@@ -60,6 +61,7 @@ mod tests {
                 gas_price: None,
                 value: None,
                 data: Some(Bytes(vec![0x04, 0x93])),
+                ..Default::default()
             },
             CallRequest {
                 from: None,
@@ -67,7 +69,8 @@ mod tests {
                 gas: None,
                 gas_price: None,
                 value: Some(0x5.into()),
-                data: Some(Bytes(vec![0x07, 0x23]))
+                data: Some(Bytes(vec![0x07, 0x23])),
+                ..Default::default()
             }
         ] => "parity_call", vec![
             r#"[{"to":"0x0000000000000000000000000000000000000123","value":"0x1"},{"data":"0x0493","from":"0x0000000000000000000000000000000000000321","to":"0x0000000000000000000000000000000000000123"},{"data":"0x0723","to":"0x0000000000000000000000000000000000000765","value":"0x5"}]"#
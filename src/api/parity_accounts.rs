@@ -31,6 +31,9 @@ impl<T: Transport> ParityAccounts<T> {
     }
     /// Imports an account from a given seed/phrase
     /// Retunrs the address of the corresponding seed vinculated account
+    ///
+    /// Note that this sends the phrase to the node, see `web3::hd_wallet` to
+    /// derive keys from a phrase locally instead.
     pub fn parity_new_account_from_phrase(&self, seed: &str, pwd: &str) -> CallFuture<Address, T::Out> {
         let seed = helpers::serialize(&seed);
         let pwd = helpers::serialize(&pwd);
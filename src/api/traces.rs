@@ -78,7 +78,8 @@ impl<T: Transport> Traces<T> {
     /// See [TraceFilterBuilder](../types/struct.TraceFilterBuilder.html)
     pub fn filter(&self, filter: TraceFilter) -> CallFuture<Vec<Trace>, T::Out> {
         let filter = helpers::serialize(&filter);
-        CallFuture::new(self.transport.execute("trace_filter", vec![filter]))
+        let (id, request) = self.transport.prepare("trace_filter", vec![filter]);
+        self.transport.send_typed(id, request)
     }
 
     /// Returns trace at the given position
@@ -214,6 +215,7 @@ mod tests {
     from: None, to: Some(Address::from_low_u64_be(0x123)),
     gas: None, gas_price: None,
     value: Some(0x1.into()), data: None,
+    ..Default::default()
     }, vec![TraceType::Trace], None
     =>
     "trace_call", vec![r#"{"to":"0x0000000000000000000000000000000000000123","value":"0x1"}"#, r#"["trace"]"#, r#""latest""#];
@@ -346,6 +346,7 @@ mod tests {
             data: None,
             nonce: None,
             condition: None,
+            ..Default::default()
         };
 
         let transaction_receipt = TransactionReceipt {
@@ -360,6 +361,7 @@ mod tests {
             status: Some(1.into()),
             root: Some(H256::zero()),
             logs_bloom: Default::default(),
+            ..Default::default()
         };
 
         let poll_interval = Duration::from_secs(0);
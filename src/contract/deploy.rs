@@ -148,6 +148,10 @@ impl<T: Transport> Builder<T> {
             nonce: options.nonce,
             data: Some(Bytes(data)),
             condition: options.condition,
+            transaction_type: options.transaction_type,
+            access_list: options.access_list,
+            max_fee_per_gas: options.max_fee_per_gas,
+            max_priority_fee_per_gas: options.max_priority_fee_per_gas,
         };
 
         let waiting = send(tx);
@@ -3,10 +3,11 @@
 use crate::api::{Accounts, Eth, Namespace};
 use crate::confirm;
 use crate::contract::tokens::{Detokenize, Tokenize};
+use crate::fees::FeeEstimator;
 use crate::signing;
 use crate::types::{
-    Address, BlockId, Bytes, CallRequest, FilterBuilder, TransactionCondition, TransactionParameters,
-    TransactionReceipt, TransactionRequest, H256, U256,
+    AccessList, Address, BlockId, Bytes, CallRequest, FilterBuilder, SignedTransaction, TransactionCondition,
+    TransactionParameters, TransactionReceipt, TransactionRequest, H256, U256, U64,
 };
 use crate::Transport;
 use futures::{
@@ -39,6 +40,14 @@ pub struct Options {
     pub nonce: Option<U256>,
     /// A condition to satisfy before including transaction.
     pub condition: Option<TransactionCondition>,
+    /// Transaction type, inferred from the other options when not set
+    pub transaction_type: Option<U64>,
+    /// Access list
+    pub access_list: Option<AccessList>,
+    /// Fixed max fee per gas (EIP-1559)
+    pub max_fee_per_gas: Option<U256>,
+    /// Fixed max priority fee per gas (EIP-1559)
+    pub max_priority_fee_per_gas: Option<U256>,
 }
 
 impl Options {
@@ -134,6 +143,10 @@ impl<T: Transport> Contract<T> {
                     value,
                     nonce,
                     condition,
+                    transaction_type,
+                    access_list,
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
                 } = options;
 
                 self.eth
@@ -146,6 +159,10 @@ impl<T: Transport> Contract<T> {
                         nonce,
                         data: Some(Bytes(data)),
                         condition,
+                        transaction_type,
+                        access_list,
+                        max_fee_per_gas,
+                        max_priority_fee_per_gas,
                     })
                     .into()
             })
@@ -161,6 +178,39 @@ impl<T: Transport> Contract<T> {
         confirmations: usize,
         key: impl signing::Key + 'a,
     ) -> impl Future<Output = crate::Result<TransactionReceipt>> + 'a {
+        self.signed_call_with(func, params, options, confirmations, move |accounts, tx| {
+            accounts.sign_transaction(tx, key)
+        })
+    }
+
+    /// Execute a signed contract function with EIP-1559 fees filled by the
+    /// given estimator and wait for confirmations
+    pub fn signed_call_with_estimator<'a>(
+        &'a self,
+        func: &'a str,
+        params: impl Tokenize,
+        options: Options,
+        confirmations: usize,
+        key: impl signing::Key + 'a,
+        estimator: impl FeeEstimator + 'a,
+    ) -> impl Future<Output = crate::Result<TransactionReceipt>> + 'a {
+        self.signed_call_with(func, params, options, confirmations, move |accounts, tx| {
+            accounts.sign_transaction_with_estimator(tx, key, estimator)
+        })
+    }
+
+    fn signed_call_with<'a, F, S>(
+        &'a self,
+        func: &'a str,
+        params: impl Tokenize,
+        options: Options,
+        confirmations: usize,
+        sign: F,
+    ) -> impl Future<Output = crate::Result<TransactionReceipt>> + 'a
+    where
+        F: FnOnce(Accounts<T>, TransactionParameters) -> S + 'a,
+        S: Future<Output = crate::Result<SignedTransaction>> + 'a,
+    {
         let poll_interval = time::Duration::from_secs(1);
 
         self.abi
@@ -173,6 +223,10 @@ impl<T: Transport> Contract<T> {
                     to: Some(self.address),
                     gas_price: options.gas_price,
                     data: Bytes(fn_data),
+                    transaction_type: options.transaction_type,
+                    access_list: options.access_list,
+                    max_fee_per_gas: options.max_fee_per_gas,
+                    max_priority_fee_per_gas: options.max_priority_fee_per_gas,
                     ..Default::default()
                 };
                 if let Some(gas) = options.gas {
@@ -181,7 +235,7 @@ impl<T: Transport> Contract<T> {
                 if let Some(value) = options.value {
                     tx.value = value;
                 }
-                let sign_future = accounts.sign_transaction(tx, key);
+                let sign_future = sign(accounts, tx);
 
                 Either::Left(sign_future.and_then(move |signed| {
                     confirm::send_raw_transaction_with_confirmation(
@@ -224,6 +278,10 @@ impl<T: Transport> Contract<T> {
                     nonce: options.nonce,
                     data: Some(Bytes(fn_data)),
                     condition: options.condition,
+                    transaction_type: options.transaction_type,
+                    access_list: options.access_list,
+                    max_fee_per_gas: options.max_fee_per_gas,
+                    max_priority_fee_per_gas: options.max_priority_fee_per_gas,
                 };
 
                 confirm::send_transaction_with_confirmation(
@@ -261,6 +319,10 @@ impl<T: Transport> Contract<T> {
                             gas_price: options.gas_price,
                             value: options.value,
                             data: Some(Bytes(data)),
+                            transaction_type: options.transaction_type,
+                            access_list: options.access_list,
+                            max_fee_per_gas: options.max_fee_per_gas,
+                            max_priority_fee_per_gas: options.max_priority_fee_per_gas,
                         },
                         None,
                     )
@@ -300,6 +362,10 @@ impl<T: Transport> Contract<T> {
                         gas_price: options.gas_price,
                         value: options.value,
                         data: Some(Bytes(call)),
+                        transaction_type: options.transaction_type,
+                        access_list: options.access_list,
+                        max_fee_per_gas: options.max_fee_per_gas,
+                        max_priority_fee_per_gas: options.max_priority_fee_per_gas,
                     },
                     block.into(),
                 );
@@ -371,11 +437,14 @@ impl<T: Transport> Contract<T> {
 #[cfg(test)]
 mod tests {
     use super::{Contract, Options};
-    use crate::api::{self, Namespace};
+    use crate::api::{self, Accounts, Namespace};
+    use crate::fees::FeeStrategy;
     use crate::helpers::tests::TestTransport;
     use crate::rpc;
-    use crate::types::{Address, BlockId, BlockNumber, H256, U256};
+    use crate::signing::SecretKey;
+    use crate::types::{Address, BlockId, BlockNumber, Bytes, TransactionParameters, TransactionReceipt, H256, U256};
     use crate::Transport;
+    use serde_json::json;
 
     fn contract<T: Transport>(transport: &T) -> Contract<&T> {
         let eth = api::Eth::new(transport);
@@ -494,6 +563,107 @@ mod tests {
         assert_eq!(result, H256::from_low_u64_be(5));
     }
 
+    #[test]
+    fn should_call_a_contract_function_with_eip1559_fees() {
+        // given
+        let mut transport = TestTransport::default();
+        transport.set_response(rpc::Value::String(format!("{:?}", H256::from_low_u64_be(5))));
+
+        let result = {
+            let token = contract(&transport);
+
+            // when
+            futures::executor::block_on(token.call(
+                "name",
+                (),
+                Address::from_low_u64_be(5),
+                Options::with(|options| {
+                    options.max_fee_per_gas = Some(0x64.into());
+                    options.max_priority_fee_per_gas = Some(0x2.into());
+                }),
+            ))
+            .unwrap()
+        };
+
+        // then
+        transport.assert_request("eth_sendTransaction", &["{\"data\":\"0x06fdde03\",\"from\":\"0x0000000000000000000000000000000000000005\",\"maxFeePerGas\":\"0x64\",\"maxPriorityFeePerGas\":\"0x2\",\"to\":\"0x0000000000000000000000000000000000000001\"}".into()]);
+        transport.assert_no_more_requests();
+        assert_eq!(result, H256::from_low_u64_be(5));
+    }
+
+    #[test]
+    fn should_sign_a_contract_call_with_estimated_fees() {
+        // given
+        let key: SecretKey = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+            .parse()
+            .unwrap();
+        let receipt = TransactionReceipt {
+            transaction_hash: H256::from_low_u64_be(5),
+            block_number: Some(2.into()),
+            ..Default::default()
+        };
+        let mut transport = TestTransport::default();
+        transport.add_response(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x64", "0x64"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x5"]]
+        }));
+        transport.add_response(json!("0x2a"));
+        transport.add_response(json!(H256::from_low_u64_be(5)));
+        transport.add_response(json!(receipt));
+
+        let result = {
+            let token = contract(&transport);
+
+            // when
+            futures::executor::block_on(token.signed_call_with_estimator(
+                "name",
+                (),
+                Options::with(|options| options.nonce = Some(0.into())),
+                0,
+                &key,
+                FeeStrategy::Medium,
+            ))
+            .unwrap()
+        };
+
+        // then
+        let expected = futures::executor::block_on(Accounts::new(TestTransport::default()).sign_transaction(
+            TransactionParameters {
+                nonce: Some(0.into()),
+                to: Some(Address::from_low_u64_be(1)),
+                data: Bytes(vec![0x06, 0xfd, 0xde, 0x03]),
+                chain_id: Some(42),
+                transaction_type: Some(2.into()),
+                max_fee_per_gas: Some(205.into()),
+                max_priority_fee_per_gas: Some(5.into()),
+                ..Default::default()
+            },
+            &key,
+        ))
+        .unwrap();
+        let raw_transaction = json!(expected.raw_transaction).to_string();
+        assert!(raw_transaction.starts_with("\"0x02"));
+
+        transport.assert_request(
+            "eth_feeHistory",
+            &[
+                json!("0xa").to_string(),
+                json!("latest").to_string(),
+                json!([50.0]).to_string(),
+            ],
+        );
+        transport.assert_request("eth_chainId", &[]);
+        transport.assert_request("eth_sendRawTransaction", &[raw_transaction]);
+        transport.assert_request(
+            "eth_getTransactionReceipt",
+            &[json!(H256::from_low_u64_be(5)).to_string()],
+        );
+        transport.assert_no_more_requests();
+        assert_eq!(result, receipt);
+    }
+
     #[test]
     fn should_estimate_gas_usage() {
         // given
@@ -20,6 +20,10 @@ pub enum Error {
     #[display(fmt = "Got invalid response: {}", _0)]
     #[from(ignore)]
     InvalidResponse(String),
+    /// invalid input provided by the caller
+    #[display(fmt = "Invalid input: {}", _0)]
+    #[from(ignore)]
+    InvalidInput(String),
     /// transport error
     #[display(fmt = "Transport error: {}", _0)]
     #[from(ignore)]
@@ -33,6 +37,9 @@ pub enum Error {
     /// recovery error
     #[display(fmt = "Recovery error: {}", _0)]
     Recovery(crate::signing::RecoveryError),
+    /// nodes did not agree on the response
+    #[display(fmt = "Quorum not reached: {}", _0)]
+    Quorum(crate::transports::quorum::QuorumError),
     /// web3 internal error
     #[display(fmt = "Internal Web3 error")]
     Internal,
@@ -42,10 +49,11 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         use self::Error::*;
         match *self {
-            Unreachable | Decoder(_) | InvalidResponse(_) | Transport(_) | Internal => None,
+            Unreachable | Decoder(_) | InvalidResponse(_) | InvalidInput(_) | Transport(_) | Internal => None,
             Rpc(ref e) => Some(e),
             Io(ref e) => Some(e),
             Recovery(ref e) => Some(e),
+            Quorum(ref e) => Some(e),
         }
     }
 }
@@ -63,10 +71,12 @@ impl Clone for Error {
             Unreachable => Unreachable,
             Decoder(s) => Decoder(s.clone()),
             InvalidResponse(s) => InvalidResponse(s.clone()),
+            InvalidInput(s) => InvalidInput(s.clone()),
             Transport(s) => Transport(s.clone()),
             Rpc(e) => Rpc(e.clone()),
             Io(e) => Io(IoError::from(e.kind())),
             Recovery(e) => Recovery(e.clone()),
+            Quorum(e) => Quorum(e.clone()),
             Internal => Internal,
         }
     }
@@ -78,12 +88,14 @@ impl PartialEq for Error {
         use self::Error::*;
         match (self, other) {
             (Unreachable, Unreachable) | (Internal, Internal) => true,
-            (Decoder(a), Decoder(b)) | (InvalidResponse(a), InvalidResponse(b)) | (Transport(a), Transport(b)) => {
-                a == b
-            }
+            (Decoder(a), Decoder(b))
+            | (InvalidResponse(a), InvalidResponse(b))
+            | (InvalidInput(a), InvalidInput(b))
+            | (Transport(a), Transport(b)) => a == b,
             (Rpc(a), Rpc(b)) => a == b,
             (Io(a), Io(b)) => a.kind() == b.kind(),
             (Recovery(a), Recovery(b)) => a == b,
+            (Quorum(a), Quorum(b)) => a.threshold == b.threshold && a.responses == b.responses,
             _ => false,
         }
     }
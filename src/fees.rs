@@ -0,0 +1,208 @@
+//! EIP-1559 fee estimation based on the fee history of recent blocks.
+
+use crate::api::{Eth, Namespace};
+use crate::error;
+use crate::types::{BlockNumber, FeeHistory, U256};
+use crate::Transport;
+use futures::{Future, FutureExt};
+
+/// Priority fee used when none of the recent blocks paid any priority fees.
+const FALLBACK_PRIORITY_FEE: U256 = U256([1_000_000_000, 0, 0, 0]);
+
+/// Fees of an EIP-1559 transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fees {
+    /// Maximum total fee per gas, including the priority fee.
+    pub max_fee_per_gas: U256,
+    /// Maximum priority fee per gas paid to the block producer.
+    pub max_priority_fee_per_gas: U256,
+}
+
+/// Estimates EIP-1559 transaction fees from the fee history of recent blocks.
+pub trait FeeEstimator {
+    /// Number of most recent blocks to request the fee history for.
+    fn block_count(&self) -> u64;
+
+    /// Percentiles of the priority fees paid in each block to request.
+    fn reward_percentiles(&self) -> Vec<f64>;
+
+    /// Estimates the transaction fees from the requested fee history.
+    fn estimate(&self, history: &FeeHistory) -> error::Result<Fees>;
+}
+
+impl<E: FeeEstimator + ?Sized> FeeEstimator for &E {
+    fn block_count(&self) -> u64 {
+        (**self).block_count()
+    }
+
+    fn reward_percentiles(&self) -> Vec<f64> {
+        (**self).reward_percentiles()
+    }
+
+    fn estimate(&self, history: &FeeHistory) -> error::Result<Fees> {
+        (**self).estimate(history)
+    }
+}
+
+/// Built-in fee estimation strategies.
+///
+/// The priority fee is the median over the last 10 blocks of the priority fee
+/// paid at the strategy's percentile of each block. The max fee leaves room
+/// for the base fee to double, which takes at least six consecutive full
+/// blocks, on top of the priority fee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeStrategy {
+    /// Priority fee paid at the 10th percentile.
+    Low,
+    /// Priority fee paid at the 50th percentile.
+    Medium,
+    /// Priority fee paid at the 90th percentile.
+    High,
+}
+
+impl FeeStrategy {
+    fn percentile(self) -> f64 {
+        match self {
+            FeeStrategy::Low => 10.0,
+            FeeStrategy::Medium => 50.0,
+            FeeStrategy::High => 90.0,
+        }
+    }
+}
+
+impl FeeEstimator for FeeStrategy {
+    fn block_count(&self) -> u64 {
+        10
+    }
+
+    fn reward_percentiles(&self) -> Vec<f64> {
+        vec![self.percentile()]
+    }
+
+    fn estimate(&self, history: &FeeHistory) -> error::Result<Fees> {
+        let base_fee = history
+            .base_fee_per_gas
+            .last()
+            .copied()
+            .ok_or_else(|| error::Error::InvalidResponse("Fee history contains no base fees".into()))?;
+
+        // Empty blocks report zero rewards, so they are left out of the median.
+        let mut rewards = history
+            .reward
+            .iter()
+            .flatten()
+            .filter_map(|rewards| rewards.first().copied())
+            .filter(|reward| !reward.is_zero())
+            .collect::<Vec<_>>();
+        rewards.sort();
+        let max_priority_fee_per_gas = rewards.get(rewards.len() / 2).copied().unwrap_or(FALLBACK_PRIORITY_FEE);
+
+        let max_fee_per_gas = base_fee
+            .checked_mul(2.into())
+            .and_then(|fee| fee.checked_add(max_priority_fee_per_gas))
+            .ok_or_else(|| error::Error::InvalidResponse("Fee history base fee is too large".into()))?;
+
+        Ok(Fees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        })
+    }
+}
+
+/// Estimates the fees of a transaction to be included in the next block.
+pub fn estimate_fees<T, E>(transport: T, estimator: E) -> impl Future<Output = error::Result<Fees>>
+where
+    T: Transport,
+    E: FeeEstimator,
+{
+    Eth::new(transport)
+        .fee_history(
+            estimator.block_count().into(),
+            BlockNumber::Latest,
+            Some(estimator.reward_percentiles()),
+        )
+        .map(move |history| estimator.estimate(&history?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::tests::TestTransport;
+    use serde_json::json;
+
+    fn history(base_fees: &[u64], rewards: &[u64]) -> FeeHistory {
+        FeeHistory {
+            oldest_block: 1.into(),
+            base_fee_per_gas: base_fees.iter().copied().map(U256::from).collect(),
+            gas_used_ratio: vec![0.5; rewards.len()],
+            reward: Some(rewards.iter().map(|reward| vec![U256::from(*reward)]).collect()),
+        }
+    }
+
+    #[test]
+    fn should_estimate_fees_from_median_reward() {
+        let history = history(&[90, 95, 100], &[3, 0, 1, 2]);
+
+        let fees = FeeStrategy::Medium.estimate(&history).unwrap();
+
+        assert_eq!(
+            fees,
+            Fees {
+                max_fee_per_gas: 202.into(),
+                max_priority_fee_per_gas: 2.into(),
+            }
+        );
+    }
+
+    #[test]
+    fn should_fall_back_when_no_rewards_were_paid() {
+        let history = history(&[100], &[0, 0]);
+
+        let fees = FeeStrategy::Low.estimate(&history).unwrap();
+
+        assert_eq!(fees.max_priority_fee_per_gas, FALLBACK_PRIORITY_FEE);
+        assert_eq!(fees.max_fee_per_gas, FALLBACK_PRIORITY_FEE + 200);
+    }
+
+    #[test]
+    fn should_fail_without_base_fees() {
+        assert!(FeeStrategy::High.estimate(&history(&[], &[])).is_err());
+    }
+
+    #[test]
+    fn should_fail_when_max_fee_overflows() {
+        let mut history = history(&[], &[1]);
+        history.base_fee_per_gas.push(U256::MAX / 2 + 1);
+
+        assert_eq!(
+            FeeStrategy::Medium.estimate(&history),
+            Err(error::Error::InvalidResponse(
+                "Fee history base fee is too large".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn should_request_fee_history() {
+        let mut transport = TestTransport::default();
+        transport.add_response(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x64", "0x64"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x5"]]
+        }));
+
+        let fees = futures::executor::block_on(estimate_fees(&transport, FeeStrategy::High)).unwrap();
+
+        transport.assert_request(
+            "eth_feeHistory",
+            &[
+                json!("0xa").to_string(),
+                json!("latest").to_string(),
+                json!([90.0]).to_string(),
+            ],
+        );
+        transport.assert_no_more_requests();
+        assert_eq!(fees.max_fee_per_gas, 205.into());
+    }
+}
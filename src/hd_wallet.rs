@@ -0,0 +1,324 @@
+//! Hierarchical deterministic wallets: BIP-39 mnemonic phrases and BIP-32
+//! key derivation along BIP-44 paths, done entirely locally.
+
+use crate::signing::{self, SecretKey, SecretKeyRef, Signature, SigningError};
+use crate::types::Address;
+use bip39::{Language, Mnemonic, Seed};
+use hmac::{Hmac, Mac, NewMac};
+use secp256k1::{PublicKey, Secp256k1};
+use sha2::Sha512;
+use std::{fmt, str::FromStr};
+
+/// Index offset of hardened child keys.
+const HARDENED: u32 = 1 << 31;
+/// HMAC key used to derive the master key from a seed.
+const MASTER_SEED_KEY: &[u8] = b"Bitcoin seed";
+
+/// Error while deriving keys from a mnemonic or seed.
+#[derive(Debug, Clone, PartialEq, Eq, derive_more::Display)]
+pub enum HdWalletError {
+    /// The mnemonic phrase is not a valid english BIP-39 phrase.
+    #[display(fmt = "Invalid mnemonic: {}", _0)]
+    InvalidMnemonic(String),
+    /// The derivation path could not be parsed.
+    #[display(fmt = "Invalid derivation path: {}", _0)]
+    InvalidPath(String),
+    /// The derived key is not a valid secp256k1 secret key.
+    ///
+    /// This happens with a probability lower than 1 in 2^127, the next index
+    /// should be used instead.
+    #[display(fmt = "Invalid derived key.")]
+    InvalidKey,
+}
+
+impl std::error::Error for HdWalletError {}
+
+/// A BIP-32 derivation path, e.g. `m/44'/60'/0'/0/0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// The BIP-44 path of the Ethereum account with the given index:
+    /// `m/44'/60'/0'/0/{index}`.
+    pub fn ethereum(index: u32) -> Self {
+        DerivationPath(vec![44 | HARDENED, 60 | HARDENED, HARDENED, 0, index])
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = HdWalletError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let invalid = || HdWalletError::InvalidPath(path.into());
+        let mut segments = path.split('/');
+        if segments.next() != Some("m") {
+            return Err(invalid());
+        }
+
+        segments
+            .map(|segment| {
+                let (index, hardened) = match segment.strip_suffix('\'') {
+                    Some(index) => (index, true),
+                    None => (segment, false),
+                };
+                if !index.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(invalid());
+                }
+                match index.parse::<u32>() {
+                    Ok(index) if index < HARDENED => Ok(if hardened { index | HARDENED } else { index }),
+                    _ => Err(invalid()),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            if index & HARDENED == 0 {
+                write!(f, "/{}", index)?;
+            } else {
+                write!(f, "/{}'", index & !HARDENED)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A BIP-32 extended private key, usable to sign transactions and messages.
+///
+/// The secret is never printed: the `Debug` implementation only shows the
+/// address of the key.
+#[derive(Clone)]
+pub struct ExtendedPrivateKey {
+    key: SecretKey,
+    chain_code: [u8; 32],
+}
+
+impl ExtendedPrivateKey {
+    /// Derives the master key from a seed.
+    pub fn master(seed: &[u8]) -> Result<Self, HdWalletError> {
+        Self::from_hmac(MASTER_SEED_KEY, &[seed])
+    }
+
+    /// Derives the child key with the given index, hardened keys having an
+    /// index of at least 2^31.
+    pub fn child(&self, index: u32) -> Result<Self, HdWalletError> {
+        let index_bytes = index.to_be_bytes();
+        let mut child = if index & HARDENED == 0 {
+            let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), &self.key);
+            Self::from_hmac(&self.chain_code, &[&public_key.serialize(), &index_bytes])?
+        } else {
+            Self::from_hmac(&self.chain_code, &[&[0], &self.key[..], &index_bytes])?
+        };
+        child
+            .key
+            .add_assign(&self.key[..])
+            .map_err(|_| HdWalletError::InvalidKey)?;
+        Ok(child)
+    }
+
+    /// Derives the key at the given path, relative to this key.
+    pub fn derive(&self, path: &DerivationPath) -> Result<Self, HdWalletError> {
+        path.0.iter().try_fold(self.clone(), |key, index| key.child(*index))
+    }
+
+    /// Returns the raw secret key bytes.
+    pub fn secret(&self) -> [u8; 32] {
+        let mut secret = [0u8; 32];
+        secret.copy_from_slice(&self.key[..]);
+        secret
+    }
+
+    fn from_hmac(key: &[u8], data: &[&[u8]]) -> Result<Self, HdWalletError> {
+        let mut mac = Hmac::<Sha512>::new_varkey(key).expect("HMAC accepts keys of any length; qed");
+        for data in data {
+            mac.update(data);
+        }
+        let output = mac.finalize().into_bytes();
+
+        let key = SecretKey::from_slice(&output[..32]).map_err(|_| HdWalletError::InvalidKey)?;
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&output[32..]);
+        Ok(ExtendedPrivateKey { key, chain_code })
+    }
+}
+
+impl fmt::Debug for ExtendedPrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ExtendedPrivateKey")
+            .field("address", &signing::Key::address(self))
+            .finish()
+    }
+}
+
+impl signing::Key for ExtendedPrivateKey {
+    fn sign(&self, message: &[u8], chain_id: Option<u64>) -> Result<Signature, SigningError> {
+        SecretKeyRef::new(&self.key).sign(message, chain_id)
+    }
+
+    fn address(&self) -> Address {
+        signing::secret_key_address(&self.key)
+    }
+}
+
+/// A wallet deriving any number of deterministic keys from a single seed.
+///
+/// ```
+/// use web3::hd_wallet::HdWallet;
+/// use web3::signing::Key;
+///
+/// let phrase = "test test test test test test test test test test test junk";
+/// let wallet = HdWallet::from_phrase(phrase, "").unwrap();
+///
+/// let first = wallet.account(0).unwrap();
+/// let same = wallet.derive(&"m/44'/60'/0'/0/0".parse().unwrap()).unwrap();
+/// assert_eq!(first.address(), same.address());
+/// assert_eq!(first.address(), "f39fd6e51aad88f6f4ce6ab8827279cfffb92266".parse().unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct HdWallet {
+    master: ExtendedPrivateKey,
+}
+
+impl HdWallet {
+    /// Creates a wallet from an english BIP-39 mnemonic phrase and an
+    /// optional password (use an empty string for none).
+    pub fn from_phrase(phrase: &str, password: &str) -> Result<Self, HdWalletError> {
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English)
+            .map_err(|e| HdWalletError::InvalidMnemonic(e.to_string()))?;
+        Self::from_seed(Seed::new(&mnemonic, password).as_bytes())
+    }
+
+    /// Creates a wallet from a BIP-32 seed.
+    pub fn from_seed(seed: &[u8]) -> Result<Self, HdWalletError> {
+        Ok(HdWallet {
+            master: ExtendedPrivateKey::master(seed)?,
+        })
+    }
+
+    /// Derives the key at the given path.
+    pub fn derive(&self, path: &DerivationPath) -> Result<ExtendedPrivateKey, HdWalletError> {
+        self.master.derive(path)
+    }
+
+    /// Derives the key of the Ethereum account with the given index, at
+    /// `m/44'/60'/0'/0/{index}`.
+    pub fn account(&self, index: u32) -> Result<ExtendedPrivateKey, HdWalletError> {
+        self.derive(&DerivationPath::ethereum(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::Key;
+    use rustc_hex::{FromHex, ToHex};
+
+    fn secret_hex(key: &ExtendedPrivateKey) -> String {
+        key.secret().to_hex()
+    }
+
+    #[test]
+    fn should_derive_bip32_test_vector() {
+        // test vector 1 taken from:
+        // https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#test-vector-1
+        let seed: Vec<u8> = "000102030405060708090a0b0c0d0e0f".from_hex().unwrap();
+        let wallet = HdWallet::from_seed(&seed).unwrap();
+
+        for (path, secret) in &[
+            ("m", "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35"),
+            (
+                "m/0'",
+                "edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea",
+            ),
+            (
+                "m/0'/1",
+                "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368",
+            ),
+            (
+                "m/0'/1/2'",
+                "cbce0d719ecf7431d88e6a89fa1483e02e35092af60c042b1df2ff59fa424dca",
+            ),
+            (
+                "m/0'/1/2'/2",
+                "0f479245fb19a38a1954c5c7c0ebab2f9bdfd96a17563ef28a6a4b1a2a764ef4",
+            ),
+            (
+                "m/0'/1/2'/2/1000000000",
+                "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8",
+            ),
+        ] {
+            let key = wallet.derive(&path.parse().unwrap()).unwrap();
+            assert_eq!(&secret_hex(&key), secret, "{}", path);
+        }
+    }
+
+    #[test]
+    fn should_derive_ethereum_accounts_from_phrase() {
+        let phrase = "test test test test test test test test test test test junk";
+        let wallet = HdWallet::from_phrase(phrase, "").unwrap();
+
+        let first = wallet.account(0).unwrap();
+        assert_eq!(
+            secret_hex(&first),
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+        );
+        assert_eq!(
+            first.address(),
+            "f39fd6e51aad88f6f4ce6ab8827279cfffb92266".parse().unwrap()
+        );
+        assert_eq!(
+            wallet.account(1).unwrap().address(),
+            "70997970c51812dc3a010c7d01b50e0d17dc79c8".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn should_use_password_for_seed() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+        let without = HdWallet::from_phrase(phrase, "").unwrap().account(0).unwrap();
+        let with = HdWallet::from_phrase(phrase, "TREZOR").unwrap().account(0).unwrap();
+
+        assert_eq!(
+            without.address(),
+            "9858effd232b4033e47d90003d41ec34ecaeda94".parse().unwrap()
+        );
+        assert_ne!(with.address(), without.address());
+    }
+
+    #[test]
+    fn should_reject_invalid_phrase() {
+        let phrase = "test test test test test test test test test test test test";
+
+        assert!(matches!(
+            HdWallet::from_phrase(phrase, ""),
+            Err(HdWalletError::InvalidMnemonic(_))
+        ));
+    }
+
+    #[test]
+    fn should_parse_and_display_derivation_path() {
+        let path: DerivationPath = "m/44'/60'/0'/0/7".parse().unwrap();
+
+        assert_eq!(path, DerivationPath::ethereum(7));
+        assert_eq!(path.to_string(), "m/44'/60'/0'/0/7");
+        assert_eq!("m".parse::<DerivationPath>().unwrap().to_string(), "m");
+    }
+
+    #[test]
+    fn should_reject_invalid_derivation_path() {
+        for path in &["", "44'/60'", "m/", "m/a", "m/-1", "m/+1", "m/2147483648", "m/0''"] {
+            assert_eq!(
+                path.parse::<DerivationPath>(),
+                Err(HdWalletError::InvalidPath(path.to_string())),
+                "{}",
+                path
+            );
+        }
+    }
+}
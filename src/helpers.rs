@@ -1,5 +1,6 @@
 //! Web3 helpers.
 
+use std::fmt;
 use std::marker::PhantomData;
 use std::marker::Unpin;
 use std::pin::Pin;
@@ -15,22 +16,46 @@ use serde_json;
 /// Value-decoder future.
 /// Takes any type which is deserializable from rpc::Value and a future which yields that
 /// type, and yields the deserialized value
-#[derive(Debug)]
 pub struct CallFuture<T, F> {
-    inner: F,
+    inner: Call<T, F>,
     _marker: PhantomData<T>,
 }
 
+enum Call<T, F> {
+    Value(F),
+    Typed(Pin<Box<dyn Future<Output = error::Result<T>> + Send>>),
+}
+
 impl<T, F> CallFuture<T, F> {
     /// Create a new CallFuture wrapping the inner future.
     pub fn new(inner: F) -> Self {
         CallFuture {
-            inner,
+            inner: Call::Value(inner),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create a new CallFuture wrapping a future which yields the deserialized value itself.
+    pub fn typed<G>(inner: G) -> Self
+    where
+        G: Future<Output = error::Result<T>> + Send + 'static,
+    {
+        CallFuture {
+            inner: Call::Typed(Box::pin(inner)),
             _marker: PhantomData,
         }
     }
 }
 
+impl<T, F: fmt::Debug> fmt::Debug for CallFuture<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.inner {
+            Call::Value(ref inner) => f.debug_struct("CallFuture").field("inner", inner).finish(),
+            Call::Typed(_) => f.debug_struct("CallFuture").field("inner", &"<typed>").finish(),
+        }
+    }
+}
+
 impl<T, F> Future for CallFuture<T, F>
 where
     T: serde::de::DeserializeOwned + Unpin,
@@ -39,8 +64,13 @@ where
     type Output = error::Result<T>;
 
     fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
-        let x = ready!(self.inner.poll_unpin(ctx));
-        Poll::Ready(x.and_then(|x| serde_json::from_value(x).map_err(Into::into)))
+        match self.inner {
+            Call::Value(ref mut inner) => {
+                let x = ready!(inner.poll_unpin(ctx));
+                Poll::Ready(x.and_then(|x| serde_json::from_value(x).map_err(Into::into)))
+            }
+            Call::Typed(ref mut inner) => inner.poll_unpin(ctx),
+        }
     }
 }
 
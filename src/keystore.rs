@@ -0,0 +1,753 @@
+//! Encrypted JSON keystore files, as defined by the Web3 Secret Storage
+//! Definition (version 3) and used by geth and Parity.
+
+use crate::signing::{self, SecretKey, SecretKeyRef, Signature, SigningError};
+use crate::types::Address;
+use aes::Aes128;
+use ctr::cipher::{NewStreamCipher, SyncStreamCipher};
+use ctr::Ctr128;
+use hmac::Hmac;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{convert::TryInto, fmt, fs, io, path::Path, str::FromStr};
+
+/// The only cipher supported by version 3 keystores.
+const CIPHER: &str = "aes-128-ctr";
+/// The only PBKDF2 pseudo-random function supported by version 3 keystores.
+const PBKDF2_PRF: &str = "hmac-sha256";
+/// Length of the derived key; the first half is the cipher key and the second
+/// half authenticates the ciphertext.
+const DERIVED_KEY_LEN: usize = 32;
+/// Largest accepted length of the derived key.
+const MAX_DERIVED_KEY_LEN: usize = 64;
+/// Largest accepted scrypt cost `n * r * p`, which is the cost of geth's
+/// standard parameters. This bounds both the memory (`128 * n * r` bytes) and
+/// the time needed to decrypt a keystore.
+const MAX_SCRYPT_COST: u64 = (1 << 18) * 8;
+/// Largest accepted number of PBKDF2 iterations, as used by geth.
+const MAX_PBKDF2_ITERATIONS: u32 = 1 << 18;
+
+/// The Salsa20/8 core function used by scrypt.
+type Salsa20_8 = salsa20::Block<salsa20::R8>;
+
+/// Error while loading, decrypting or encrypting a keystore.
+#[derive(Debug, derive_more::Display)]
+pub enum KeystoreError {
+    /// The keystore file could not be read or written.
+    #[display(fmt = "IO error: {}", _0)]
+    Io(io::Error),
+    /// The keystore is malformed or uses unsupported parameters.
+    #[display(fmt = "Invalid keystore: {}", _0)]
+    InvalidKeystore(String),
+    /// The MAC of the keystore does not match, the password is most likely wrong.
+    #[display(fmt = "Invalid password.")]
+    InvalidPassword,
+    /// The (decrypted) secret is not a valid secp256k1 secret key.
+    #[display(fmt = "Invalid secret key.")]
+    InvalidKey,
+}
+
+impl std::error::Error for KeystoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            KeystoreError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for KeystoreError {
+    fn from(err: io::Error) -> Self {
+        KeystoreError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for KeystoreError {
+    fn from(err: serde_json::Error) -> Self {
+        KeystoreError::InvalidKeystore(err.to_string())
+    }
+}
+
+/// A private key that can be used for signing and stored in a keystore.
+///
+/// The secret is never printed: the `Debug` implementation only shows the
+/// address of the key.
+pub struct PrivateKey {
+    key: SecretKey,
+}
+
+impl PrivateKey {
+    /// Creates a private key from its 32 raw bytes.
+    pub fn from_slice(secret: &[u8]) -> Result<Self, KeystoreError> {
+        let key = SecretKey::from_slice(secret).map_err(|_| KeystoreError::InvalidKey)?;
+        Ok(PrivateKey { key })
+    }
+
+    /// Generates a new random private key.
+    pub fn random() -> Self {
+        let mut rng = rand::thread_rng();
+        loop {
+            let mut secret = [0u8; 32];
+            rng.fill_bytes(&mut secret);
+            // Practically all 32 byte values are valid secret keys.
+            if let Ok(key) = Self::from_slice(&secret) {
+                return key;
+            }
+        }
+    }
+}
+
+impl FromStr for PrivateKey {
+    type Err = KeystoreError;
+
+    /// Parses a hex encoded private key, with or without a `0x` prefix.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let secret: Vec<u8> =
+            rustc_hex::FromHex::from_hex(s.trim_start_matches("0x")).map_err(|_| KeystoreError::InvalidKey)?;
+        Self::from_slice(&secret)
+    }
+}
+
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PrivateKey")
+            .field("address", &signing::Key::address(self))
+            .finish()
+    }
+}
+
+impl signing::Key for PrivateKey {
+    fn sign(&self, message: &[u8], chain_id: Option<u64>) -> Result<Signature, SigningError> {
+        SecretKeyRef::new(&self.key).sign(message, chain_id)
+    }
+
+    fn address(&self) -> Address {
+        signing::secret_key_address(&self.key)
+    }
+}
+
+/// The key derivation function used to encrypt a keystore.
+///
+/// Parameters more expensive than geth's standard ones (scrypt with
+/// `n * r * p` above `2^18 * 8`, or PBKDF2 with more than `2^18` iterations)
+/// are rejected, both when encrypting and decrypting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kdf {
+    /// scrypt with a CPU/memory cost of `2^log_n`, block size `r` and
+    /// parallelization `p`.
+    Scrypt {
+        /// Base 2 logarithm of the CPU/memory cost.
+        log_n: u8,
+        /// Block size.
+        r: u32,
+        /// Parallelization.
+        p: u32,
+    },
+    /// PBKDF2-HMAC-SHA256 with `c` iterations.
+    Pbkdf2 {
+        /// Number of iterations.
+        c: u32,
+    },
+}
+
+impl Default for Kdf {
+    /// The standard scrypt parameters used by geth.
+    fn default() -> Self {
+        Kdf::Scrypt { log_n: 18, r: 8, p: 1 }
+    }
+}
+
+/// An encrypted keystore file.
+///
+/// The file can be (de)serialized with `serde_json`, or loaded and saved
+/// with `KeyFile::load` and `KeyFile::save`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyFile {
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "hex_option")]
+    address: Option<Vec<u8>>,
+    #[serde(alias = "Crypto")]
+    crypto: Crypto,
+    id: String,
+    version: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Crypto {
+    cipher: String,
+    cipherparams: CipherParams,
+    #[serde(with = "hex")]
+    ciphertext: Vec<u8>,
+    #[serde(flatten)]
+    kdf: KdfParams,
+    #[serde(with = "hex")]
+    mac: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CipherParams {
+    #[serde(with = "hex")]
+    iv: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kdf", content = "kdfparams", rename_all = "lowercase")]
+enum KdfParams {
+    Scrypt {
+        dklen: usize,
+        n: u64,
+        p: u32,
+        r: u32,
+        #[serde(with = "hex")]
+        salt: Vec<u8>,
+    },
+    Pbkdf2 {
+        c: u32,
+        dklen: usize,
+        prf: String,
+        #[serde(with = "hex")]
+        salt: Vec<u8>,
+    },
+}
+
+impl KdfParams {
+    fn derive_key(&self, password: &[u8]) -> Result<Vec<u8>, KeystoreError> {
+        let invalid = |msg: &str| KeystoreError::InvalidKeystore(msg.into());
+        match self {
+            KdfParams::Scrypt { dklen, n, p, r, salt } => {
+                if *dklen < DERIVED_KEY_LEN || *dklen > MAX_DERIVED_KEY_LEN {
+                    return Err(invalid("invalid scrypt dklen"));
+                }
+                if !n.is_power_of_two() || *n < 2 {
+                    return Err(invalid("scrypt n must be a power of 2 greater than 1"));
+                }
+                if *r == 0 || *p == 0 {
+                    return Err(invalid("scrypt r and p must be positive"));
+                }
+                let cost = n
+                    .checked_mul(u64::from(*r))
+                    .and_then(|cost| cost.checked_mul(u64::from(*p)));
+                if !matches!(cost, Some(cost) if cost <= MAX_SCRYPT_COST) {
+                    return Err(invalid("scrypt parameters are too expensive"));
+                }
+                let mut key = vec![0u8; *dklen];
+                scrypt(password, salt, *n as usize, *r as usize, *p as usize, &mut key);
+                Ok(key)
+            }
+            KdfParams::Pbkdf2 { c, dklen, prf, salt } => {
+                if *dklen < DERIVED_KEY_LEN || *dklen > MAX_DERIVED_KEY_LEN {
+                    return Err(invalid("invalid pbkdf2 dklen"));
+                }
+                if *c == 0 {
+                    return Err(invalid("pbkdf2 c must be positive"));
+                }
+                if *c > MAX_PBKDF2_ITERATIONS {
+                    return Err(invalid("pbkdf2 c is too large"));
+                }
+                if prf != PBKDF2_PRF {
+                    return Err(KeystoreError::InvalidKeystore(format!(
+                        "unsupported pbkdf2 prf {}",
+                        prf
+                    )));
+                }
+                let mut key = vec![0u8; *dklen];
+                pbkdf2::pbkdf2::<Hmac<Sha256>>(password, salt, *c, &mut key);
+                Ok(key)
+            }
+        }
+    }
+}
+
+impl KeyFile {
+    /// Loads a keystore file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, KeystoreError> {
+        let file = fs::File::open(path)?;
+        Ok(serde_json::from_reader(io::BufReader::new(file))?)
+    }
+
+    /// Saves the keystore to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), KeystoreError> {
+        let file = fs::File::create(path)?;
+        serde_json::to_writer(io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// The UUID of the keystore.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The address of the encrypted key, when present in the keystore.
+    ///
+    /// Note that this address is not authenticated and may not match the key.
+    pub fn address(&self) -> Option<Address> {
+        match self.address {
+            Some(ref address) if address.len() == 20 => Some(Address::from_slice(address)),
+            _ => None,
+        }
+    }
+
+    /// Decrypts the private key with the given password.
+    pub fn decrypt(&self, password: impl AsRef<[u8]>) -> Result<PrivateKey, KeystoreError> {
+        if self.version != 3 {
+            return Err(KeystoreError::InvalidKeystore(format!(
+                "unsupported version {}",
+                self.version
+            )));
+        }
+        let crypto = &self.crypto;
+        if crypto.cipher != CIPHER {
+            return Err(KeystoreError::InvalidKeystore(format!(
+                "unsupported cipher {}",
+                crypto.cipher
+            )));
+        }
+
+        let derived_key = crypto.kdf.derive_key(password.as_ref())?;
+        if !constant_time_eq(&mac(&derived_key, &crypto.ciphertext), &crypto.mac) {
+            return Err(KeystoreError::InvalidPassword);
+        }
+
+        let mut secret = crypto.ciphertext.clone();
+        apply_cipher(&derived_key, &crypto.cipherparams.iv, &mut secret)?;
+        PrivateKey::from_slice(&secret)
+    }
+
+    /// Encrypts a private key with the given password, using a random salt,
+    /// IV and UUID.
+    pub fn encrypt(key: &PrivateKey, password: impl AsRef<[u8]>, kdf: Kdf) -> Result<Self, KeystoreError> {
+        let mut rng = rand::thread_rng();
+        let salt: [u8; 32] = rng.gen();
+        let iv: [u8; 16] = rng.gen();
+        let id = uuid::Builder::from_bytes(rng.gen())
+            .set_variant(uuid::Variant::RFC4122)
+            .set_version(uuid::Version::Random)
+            .build();
+
+        Self::encrypt_with(key, password.as_ref(), kdf, &salt, &iv, id.to_string())
+    }
+
+    fn encrypt_with(
+        key: &PrivateKey,
+        password: &[u8],
+        kdf: Kdf,
+        salt: &[u8],
+        iv: &[u8],
+        id: String,
+    ) -> Result<Self, KeystoreError> {
+        let salt = salt.to_vec();
+        let kdf = match kdf {
+            Kdf::Scrypt { log_n, r, p } => KdfParams::Scrypt {
+                dklen: DERIVED_KEY_LEN,
+                n: 1u64.checked_shl(log_n.into()).unwrap_or(0),
+                p,
+                r,
+                salt,
+            },
+            Kdf::Pbkdf2 { c } => KdfParams::Pbkdf2 {
+                c,
+                dklen: DERIVED_KEY_LEN,
+                prf: PBKDF2_PRF.into(),
+                salt,
+            },
+        };
+
+        let derived_key = kdf.derive_key(password)?;
+        let mut ciphertext = key.key[..].to_vec();
+        apply_cipher(&derived_key, iv, &mut ciphertext)?;
+
+        Ok(KeyFile {
+            address: Some(signing::Key::address(key).as_bytes().to_vec()),
+            crypto: Crypto {
+                cipher: CIPHER.into(),
+                cipherparams: CipherParams { iv: iv.to_vec() },
+                mac: mac(&derived_key, &ciphertext),
+                ciphertext,
+                kdf,
+            },
+            id,
+            version: 3,
+        })
+    }
+}
+
+/// Encrypts or decrypts the data in place with AES-128-CTR.
+fn apply_cipher(derived_key: &[u8], iv: &[u8], data: &mut [u8]) -> Result<(), KeystoreError> {
+    let mut cipher = Ctr128::<Aes128>::new_var(&derived_key[..16], iv)
+        .map_err(|_| KeystoreError::InvalidKeystore("invalid cipher IV length".into()))?;
+    cipher.apply_keystream(data);
+    Ok(())
+}
+
+/// Computes the MAC of the ciphertext.
+fn mac(derived_key: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    let mut data = derived_key[16..DERIVED_KEY_LEN].to_vec();
+    data.extend_from_slice(ciphertext);
+    signing::keccak256(&data).to_vec()
+}
+
+/// Derives a key with scrypt as defined by RFC 7914.
+///
+/// Unlike the `scrypt` crate this does not enforce the `n < 2^(16 * r)` bound
+/// of the RFC, which keystores (including the test vector of the Web3 Secret
+/// Storage Definition) do not always satisfy. The parameters must have been
+/// checked against `MAX_SCRYPT_COST` already.
+fn scrypt(password: &[u8], salt: &[u8], n: usize, r: usize, p: usize, output: &mut [u8]) {
+    let block_len = 128 * r;
+    let mut b = vec![0u8; p * block_len];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password, salt, 1, &mut b);
+
+    let mut v = vec![0u8; n * block_len];
+    let mut t = vec![0u8; block_len];
+    for block in b.chunks_mut(block_len) {
+        scrypt_ro_mix(block, &mut v, &mut t, n);
+    }
+
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password, &b, 1, output);
+}
+
+/// The scrypt ROMix function, applied to `b` in place using `v` and `t` as
+/// scratch space.
+fn scrypt_ro_mix(b: &mut [u8], v: &mut [u8], t: &mut [u8], n: usize) {
+    let len = b.len();
+    for chunk in v.chunks_mut(len) {
+        chunk.copy_from_slice(b);
+        scrypt_block_mix(chunk, b);
+    }
+
+    for _ in 0..n {
+        // Integerify: the first word of the last 64 byte block, modulo n.
+        let word = u32::from_le_bytes(b[len - 64..len - 60].try_into().expect("slice of 4 bytes; qed"));
+        let j = word as usize & (n - 1);
+        for ((t_i, b_i), v_i) in t.iter_mut().zip(b.iter()).zip(&v[j * len..(j + 1) * len]) {
+            *t_i = b_i ^ v_i;
+        }
+        scrypt_block_mix(t, b);
+    }
+}
+
+/// The scrypt BlockMix function over Salsa20/8.
+fn scrypt_block_mix(input: &[u8], output: &mut [u8]) {
+    let mut x = [0u8; 64];
+    x.copy_from_slice(&input[input.len() - 64..]);
+
+    for (i, chunk) in input.chunks(64).enumerate() {
+        let mut state = [0u32; 16];
+        for ((word, x), chunk) in state.iter_mut().zip(x.chunks(4)).zip(chunk.chunks(4)) {
+            *word = u32::from_le_bytes([x[0] ^ chunk[0], x[1] ^ chunk[1], x[2] ^ chunk[2], x[3] ^ chunk[3]]);
+        }
+        Salsa20_8::from(state).generate(&mut x);
+
+        // Even blocks go to the first half of the output, odd ones to the second.
+        let at = (i / 2) * 64 + (i % 2) * (input.len() / 2);
+        output[at..at + 64].copy_from_slice(&x);
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// (De)serializes bytes as hex strings without `0x` prefix, which is how
+/// keystores encode them.
+mod hex {
+    use rustc_hex::{FromHex, ToHex};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&bytes.to_hex::<String>())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value
+            .trim_start_matches("0x")
+            .from_hex()
+            .map_err(|e| D::Error::custom(format!("Invalid hex: {}", e)))
+    }
+}
+
+mod hex_option {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => super::hex::serialize(bytes, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error> {
+        #[derive(Deserialize)]
+        struct Hex(#[serde(with = "super::hex")] Vec<u8>);
+
+        Ok(Option::<Hex>::deserialize(deserializer)?.map(|hex| hex.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::Key;
+
+    // test vectors taken from:
+    // https://github.com/ethereum/wiki/wiki/Web3-Secret-Storage-Definition#test-vectors
+
+    const PASSWORD: &str = "testpassword";
+    const SECRET: &str = "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d";
+
+    const PBKDF2_KEYSTORE: &str = r#"{
+    "crypto" : {
+        "cipher" : "aes-128-ctr",
+        "cipherparams" : {
+            "iv" : "6087dab2f9fdbbfaddc31a909735c1e6"
+        },
+        "ciphertext" : "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+        "kdf" : "pbkdf2",
+        "kdfparams" : {
+            "c" : 262144,
+            "dklen" : 32,
+            "prf" : "hmac-sha256",
+            "salt" : "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+        },
+        "mac" : "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+    },
+    "id" : "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+    "version" : 3
+}"#;
+
+    const SCRYPT_KEYSTORE: &str = r#"{
+    "crypto" : {
+        "cipher" : "aes-128-ctr",
+        "cipherparams" : {
+            "iv" : "83dbcc02d8ccb40e466191a123791e0e"
+        },
+        "ciphertext" : "d172bf743a674da9cdad04534d56926ef8358534d458fffccd4e6ad2fbde479c",
+        "kdf" : "scrypt",
+        "kdfparams" : {
+            "dklen" : 32,
+            "n" : 262144,
+            "p" : 8,
+            "r" : 1,
+            "salt" : "ab0c7876052600dd703518d6fc3fe8984592145b591fc8fb5c6d43190334ba19"
+        },
+        "mac" : "2103ac29920d71da29f15d75b4a16dbe95cfd7ff8faea1056c33131d846e3097"
+    },
+    "id" : "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+    "version" : 3
+}"#;
+
+    fn secret() -> PrivateKey {
+        SECRET.parse().unwrap()
+    }
+
+    /// Checks that the keystore decrypts to the test vector key and that
+    /// encrypting the key with the same parameters yields the same keystore.
+    fn assert_test_vector(json: &str, kdf: Kdf) {
+        let keystore: KeyFile = serde_json::from_str(json).unwrap();
+
+        let key = keystore.decrypt(PASSWORD).unwrap();
+        assert_eq!(key.address(), secret().address());
+
+        let salt = match keystore.crypto.kdf {
+            KdfParams::Scrypt { ref salt, .. } | KdfParams::Pbkdf2 { ref salt, .. } => salt,
+        };
+        let encrypted = KeyFile::encrypt_with(
+            &key,
+            PASSWORD.as_bytes(),
+            kdf,
+            salt,
+            &keystore.crypto.cipherparams.iv,
+            keystore.id.clone(),
+        )
+        .unwrap();
+        assert_eq!(encrypted.crypto, keystore.crypto);
+    }
+
+    #[test]
+    fn should_decrypt_and_encrypt_pbkdf2_test_vector() {
+        assert_test_vector(PBKDF2_KEYSTORE, Kdf::Pbkdf2 { c: 262_144 });
+    }
+
+    #[test]
+    #[ignore = "takes half a minute in debug builds, scrypt is covered by the tests below"]
+    fn should_decrypt_scrypt_test_vector() {
+        // encrypting is covered with the cheaper parameters of the light keystore below
+        let keystore: KeyFile = serde_json::from_str(SCRYPT_KEYSTORE).unwrap();
+
+        assert_eq!(keystore.decrypt(PASSWORD).unwrap().address(), secret().address());
+    }
+
+    #[test]
+    fn should_decrypt_geth_light_scrypt_keystore() {
+        // the test vector key encrypted with geth's "light" parameters, which
+        // satisfy the RFC 7914 bound on n, unlike the test vector
+        let json = SCRYPT_KEYSTORE
+            .replace(
+                "d172bf743a674da9cdad04534d56926ef8358534d458fffccd4e6ad2fbde479c",
+                "584f4eb2783472ec149b334c22d77ddbf118a0783ea24e66ca8a7883cd2b4bf5",
+            )
+            .replace(r#""n" : 262144"#, r#""n" : 4096"#)
+            .replace(r#""p" : 8"#, r#""p" : 6"#)
+            .replace(r#""r" : 1"#, r#""r" : 8"#)
+            .replace(
+                "2103ac29920d71da29f15d75b4a16dbe95cfd7ff8faea1056c33131d846e3097",
+                "860ad3ce9642f99d709abc696e25ede7cd35daf6a6041e975d14c53dae118886",
+            );
+
+        assert_test_vector(&json, Kdf::Scrypt { log_n: 12, r: 8, p: 6 });
+    }
+
+    #[test]
+    fn should_reject_expensive_kdf_parameters() {
+        for (from, to) in &[
+            (r#""n" : 262144"#, r#""n" : 524288"#),
+            (r#""p" : 8"#, r#""p" : 9"#),
+            (r#""r" : 1"#, r#""r" : 4294967295"#),
+            (r#""dklen" : 32"#, r#""dklen" : 4294967296"#),
+            (r#""n" : 262144"#, r#""n" : 1"#),
+            (r#""n" : 262144"#, r#""n" : 0"#),
+            (r#""p" : 8"#, r#""p" : 0"#),
+        ] {
+            let keystore: KeyFile = serde_json::from_str(&SCRYPT_KEYSTORE.replace(from, to)).unwrap();
+            assert!(
+                matches!(keystore.decrypt(PASSWORD), Err(KeystoreError::InvalidKeystore(_))),
+                "{}",
+                to
+            );
+        }
+
+        for (from, to) in &[
+            (r#""c" : 262144"#, r#""c" : 262145"#),
+            (r#""c" : 262144"#, r#""c" : 0"#),
+            (r#""dklen" : 32"#, r#""dklen" : 4294967296"#),
+        ] {
+            let keystore: KeyFile = serde_json::from_str(&PBKDF2_KEYSTORE.replace(from, to)).unwrap();
+            assert!(
+                matches!(keystore.decrypt(PASSWORD), Err(KeystoreError::InvalidKeystore(_))),
+                "{}",
+                to
+            );
+        }
+
+        for kdf in &[
+            Kdf::Scrypt { log_n: 0, r: 8, p: 1 },
+            Kdf::Scrypt { log_n: 19, r: 8, p: 1 },
+            Kdf::Pbkdf2 { c: 0 },
+        ] {
+            assert!(
+                matches!(
+                    KeyFile::encrypt(&secret(), PASSWORD, *kdf),
+                    Err(KeystoreError::InvalidKeystore(_))
+                ),
+                "{:?}",
+                kdf
+            );
+        }
+    }
+
+    #[test]
+    fn should_derive_scrypt_test_vectors() {
+        // test vectors taken from RFC 7914, section 12, and computed with a
+        // reference implementation for the remaining block layouts
+        for (password, salt, n, r, p, expected) in &[
+            (
+                "",
+                "",
+                16,
+                1,
+                1,
+                "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906",
+            ),
+            (
+                "password",
+                "NaCl",
+                1024,
+                8,
+                16,
+                "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b3731622eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640",
+            ),
+            // r > 1 with a single block
+            (
+                "password",
+                "NaCl",
+                16,
+                2,
+                1,
+                "696fd52c8cec830c2de2e0f016b0dfcbe9ab55e072c754d56fb9230a752c5faf4fd914a4edad8c508d0d35040aad8e8b7795698998d9c21505eaea188dafde7a",
+            ),
+            // p > 1 with the smallest block size
+            (
+                "password",
+                "NaCl",
+                16,
+                1,
+                3,
+                "37026a79f08b71a4e83abf2f84fb15fb556302d65294baaaa4cd98fce5b32fc73212f26d2a0a14a42e9e5549c898a3edfd2d003ceefbba8a2e05d7a5e46570f6",
+            ),
+            // n beyond the RFC 7914 bound of 2^(16 * r)
+            (
+                "password",
+                "NaCl",
+                65536,
+                1,
+                1,
+                "e31d00a86544bc78c07b2cbcb9df8fa5add5e6390e28153509c032382914c0b6c28f0577f3a70de439b473e5d53b49ceac9d31e7208ee2983381f6c5474596f4",
+            ),
+        ] {
+            let mut key = [0u8; 64];
+            scrypt(password.as_bytes(), salt.as_bytes(), *n, *r, *p, &mut key);
+            assert_eq!(rustc_hex::ToHex::to_hex::<String>(&key[..]), *expected, "{} {} {}", n, r, p);
+        }
+    }
+
+    #[test]
+    fn should_reject_wrong_password() {
+        let keystore: KeyFile = serde_json::from_str(PBKDF2_KEYSTORE).unwrap();
+
+        assert!(matches!(
+            keystore.decrypt("wrongpassword"),
+            Err(KeystoreError::InvalidPassword)
+        ));
+    }
+
+    #[test]
+    fn should_reject_unsupported_kdf() {
+        let json = PBKDF2_KEYSTORE.replace(r#""kdf" : "pbkdf2""#, r#""kdf" : "argon2""#);
+
+        assert!(serde_json::from_str::<KeyFile>(&json).is_err());
+    }
+
+    #[test]
+    fn should_save_and_load_keystore() {
+        let key = PrivateKey::random();
+        let keystore = KeyFile::encrypt(&key, "password", Kdf::Scrypt { log_n: 10, r: 8, p: 1 }).unwrap();
+        assert_eq!(keystore.address(), Some(key.address()));
+        assert_eq!(keystore.id().len(), 36);
+
+        let path = std::env::temp_dir().join(format!("web3-keystore-{}.json", keystore.id()));
+        keystore.save(&path).unwrap();
+        let loaded = KeyFile::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded, keystore);
+        assert_eq!(loaded.decrypt("password").unwrap().address(), key.address());
+    }
+
+    #[test]
+    fn should_load_geth_keystore() {
+        // geth stores the address without 0x prefix
+        let json = PBKDF2_KEYSTORE.replacen("{", r#"{ "address": "008aeeda4d805471df9b2a5b0f38a0c3bcba786b","#, 1);
+        let keystore: KeyFile = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            keystore.address(),
+            Some("008aeeda4d805471df9b2a5b0f38a0c3bcba786b".parse().unwrap())
+        );
+        assert_eq!(
+            keystore.decrypt(PASSWORD).unwrap().address(),
+            keystore.address().unwrap()
+        );
+    }
+}
@@ -27,6 +27,11 @@ pub mod api;
 pub mod confirm;
 pub mod contract;
 pub mod error;
+pub mod fees;
+#[cfg(feature = "hd-wallet")]
+pub mod hd_wallet;
+#[cfg(feature = "keystore")]
+pub mod keystore;
 pub mod signing;
 pub mod transports;
 pub mod types;
@@ -55,6 +60,18 @@ pub trait Transport: std::fmt::Debug + Clone + Unpin {
         let (id, request) = self.prepare(method, params);
         self.send(id, request)
     }
+
+    /// Execute prepared RPC call with a result of known type.
+    ///
+    /// By default the result is deserialized from the `rpc::Value` returned by `send`. Transports
+    /// able to deserialize the result while the response is received override this to avoid
+    /// holding large responses in memory.
+    fn send_typed<R>(&self, id: RequestId, request: rpc::Call) -> helpers::CallFuture<R, Self::Out>
+    where
+        R: serde::de::DeserializeOwned + Send + 'static,
+    {
+        helpers::CallFuture::new(self.send(id, request))
+    }
 }
 
 /// A transport implementation supporting batch requests.
@@ -97,6 +114,13 @@ where
     fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
         (**self).send(id, request)
     }
+
+    fn send_typed<R>(&self, id: RequestId, request: rpc::Call) -> helpers::CallFuture<R, Self::Out>
+    where
+        R: serde::de::DeserializeOwned + Send + 'static,
+    {
+        (**self).send_typed(id, request)
+    }
 }
 
 impl<X, T> BatchTransport for X
@@ -0,0 +1,546 @@
+//! Load-balancing Transport
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::{self, Error};
+use crate::helpers;
+use crate::rpc;
+use crate::{BatchTransport, RequestId, Transport};
+use futures::task::{Context, Poll};
+use futures::{Future, FutureExt};
+use parking_lot::Mutex;
+
+/// Order in which endpoints are tried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    /// Always use the first healthy endpoint, the other ones are backups.
+    Failover,
+    /// Rotate healthy endpoints with every request.
+    RoundRobin,
+    /// Use the healthy endpoint with the lowest average response time.
+    LowestLatency,
+}
+
+/// Conditions of taking endpoints out of and back into rotation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthPolicy {
+    /// Number of consecutive failures after which the endpoint is marked unhealthy.
+    pub failure_threshold: usize,
+    /// Delay between `eth_blockNumber` probes of an unhealthy endpoint.
+    pub probe_interval: Duration,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        HealthPolicy {
+            failure_threshold: 3,
+            probe_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    failures: usize,
+    unhealthy: bool,
+    probing: bool,
+    last_probe: Option<Instant>,
+    latency: Option<Duration>,
+}
+
+impl Health {
+    fn record_latency(&mut self, latency: Duration) {
+        self.latency = Some(match self.latency {
+            Some(average) => average * 3 / 4 + latency / 4,
+            None => latency,
+        });
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint<T> {
+    transport: T,
+    health: Mutex<Health>,
+}
+
+/// An `eth_blockNumber` request checking if an unhealthy endpoint is back.
+struct Probe<F> {
+    idx: usize,
+    started: Instant,
+    future: F,
+}
+
+impl<F> fmt::Debug for Probe<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Probe")
+            .field("idx", &self.idx)
+            .field("started", &self.started)
+            .finish()
+    }
+}
+
+struct Endpoints<T: Transport> {
+    endpoints: Vec<Endpoint<T>>,
+    strategy: Strategy,
+    policy: HealthPolicy,
+    next: AtomicUsize,
+    /// Probes in flight, polled by every request until they complete.
+    probes: Mutex<Vec<Probe<T::Out>>>,
+}
+
+impl<T: Transport + fmt::Debug> fmt::Debug for Endpoints<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Endpoints")
+            .field("endpoints", &self.endpoints)
+            .field("strategy", &self.strategy)
+            .field("policy", &self.policy)
+            .field("next", &self.next)
+            .field("probes", &self.probes)
+            .finish()
+    }
+}
+
+impl<T: Transport> Endpoints<T> {
+    /// Returns indices of endpoints in the order they should be tried.
+    ///
+    /// Unhealthy endpoints are used only as a last resort.
+    fn order(&self) -> Vec<usize> {
+        let (mut healthy, unhealthy): (Vec<_>, Vec<_>) =
+            (0..self.endpoints.len()).partition(|&idx| !self.endpoints[idx].health.lock().unhealthy);
+        match self.strategy {
+            Strategy::Failover => {}
+            Strategy::RoundRobin => {
+                let len = healthy.len();
+                if len > 0 {
+                    let next = self.next.fetch_add(1, atomic::Ordering::Relaxed);
+                    healthy.rotate_left(next % len);
+                }
+            }
+            Strategy::LowestLatency => {
+                healthy.sort_by_key(|&idx| self.endpoints[idx].health.lock().latency.unwrap_or_default());
+            }
+        }
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    /// Returns indices of unhealthy endpoints which should be probed now.
+    fn due_probes(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let interval = self.policy.probe_interval;
+        (0..self.endpoints.len())
+            .filter(|&idx| {
+                let mut health = self.endpoints[idx].health.lock();
+                let due = health.unhealthy
+                    && !health.probing
+                    && !matches!(health.last_probe, Some(last) if now.duration_since(last) < interval);
+                if due {
+                    health.probing = true;
+                    health.last_probe = Some(now);
+                }
+                due
+            })
+            .collect()
+    }
+
+    /// Polls the probes in flight, recording the health of the endpoints which responded.
+    ///
+    /// The probes are shared by all requests, so they complete even if they take longer than the
+    /// request they were sent along with.
+    fn poll_probes(&self, cx: &mut Context) {
+        // Another request polling the probes at the same time will record their results.
+        let mut probes = match self.probes.try_lock() {
+            Some(probes) => probes,
+            None => return,
+        };
+        let mut i = 0;
+        while i < probes.len() {
+            let probe = &mut probes[i];
+            match probe.future.poll_unpin(cx) {
+                Poll::Ready(result) => {
+                    let latency = probe.started.elapsed();
+                    self.probed(probe.idx, result.ok().map(|_| latency));
+                    let _ = probes.swap_remove(i);
+                }
+                Poll::Pending => i += 1,
+            }
+        }
+    }
+
+    fn succeeded(&self, idx: usize, latency: Duration) {
+        let mut health = self.endpoints[idx].health.lock();
+        health.failures = 0;
+        health.record_latency(latency);
+    }
+
+    fn failed(&self, idx: usize) {
+        let mut health = self.endpoints[idx].health.lock();
+        health.failures += 1;
+        if !health.unhealthy && health.failures >= self.policy.failure_threshold {
+            log::warn!("Endpoint {} marked unhealthy after {} failures.", idx, health.failures);
+            health.unhealthy = true;
+            health.last_probe = Some(Instant::now());
+        }
+    }
+
+    fn probed(&self, idx: usize, latency: Option<Duration>) {
+        let mut health = self.endpoints[idx].health.lock();
+        health.probing = false;
+        if let Some(latency) = latency {
+            log::info!("Endpoint {} is healthy again.", idx);
+            health.unhealthy = false;
+            health.failures = 0;
+            health.record_latency(latency);
+        }
+    }
+}
+
+/// Returns true if the request should be sent to another endpoint after failing with given error.
+fn is_endpoint_failure(error: &Error) -> bool {
+    matches!(error, Error::Unreachable | Error::Transport(_) | Error::Io(_))
+}
+
+/// Transport distributing requests across several endpoints.
+///
+/// Requests failing because of the endpoint (`Error::Unreachable`, `Error::Transport` or `Error::Io`)
+/// are sent to the next endpoint. Endpoints failing repeatedly are taken out of rotation until they
+/// respond to `eth_blockNumber` again; the probes are sent along with subsequent requests and
+/// polled by every following request until they complete.
+///
+/// Request ids are assigned by this transport, so they are the same regardless of the endpoint used.
+#[derive(Debug, Clone)]
+pub struct Balanced<T: Transport> {
+    id: Arc<AtomicUsize>,
+    endpoints: Arc<Endpoints<T>>,
+}
+
+impl<T: Transport> Balanced<T> {
+    /// Creates new transport distributing requests according to given strategy.
+    pub fn new(transports: Vec<T>, strategy: Strategy) -> Self {
+        Self::with_health_policy(transports, strategy, Default::default())
+    }
+
+    /// Creates new transport with custom health checks.
+    pub fn with_health_policy(transports: Vec<T>, strategy: Strategy, policy: HealthPolicy) -> Self {
+        let endpoints = transports
+            .into_iter()
+            .map(|transport| Endpoint {
+                transport,
+                health: Default::default(),
+            })
+            .collect();
+        Balanced {
+            id: Arc::new(AtomicUsize::new(1)),
+            endpoints: Arc::new(Endpoints {
+                endpoints,
+                strategy,
+                policy,
+                next: AtomicUsize::new(0),
+                probes: Default::default(),
+            }),
+        }
+    }
+
+    /// Returns false if the endpoint at given index is out of rotation.
+    pub fn is_healthy(&self, index: usize) -> bool {
+        matches!(self.endpoints.endpoints.get(index), Some(endpoint) if !endpoint.health.lock().unhealthy)
+    }
+
+    fn request<R, F>(&self, request: R, send: fn(&T, &R) -> F) -> BalancedFuture<T, R, F> {
+        let probes = self.endpoints.due_probes().into_iter().map(|idx| {
+            let id = self.id.fetch_add(1, atomic::Ordering::AcqRel);
+            let call = helpers::build_request(id, "eth_blockNumber", vec![]);
+            Probe {
+                idx,
+                started: Instant::now(),
+                future: self.endpoints.endpoints[idx].transport.send(id, call),
+            }
+        });
+        self.endpoints.probes.lock().extend(probes);
+        BalancedFuture {
+            endpoints: self.endpoints.clone(),
+            request,
+            send,
+            remaining: self.endpoints.order().into_iter(),
+            current: None,
+            last_error: None,
+        }
+    }
+}
+
+impl<T: Transport> Transport for Balanced<T> {
+    type Out = BalancedFuture<T, (RequestId, rpc::Call), T::Out>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        let id = self.id.fetch_add(1, atomic::Ordering::AcqRel);
+        let request = helpers::build_request(id, method, params);
+
+        (id, request)
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        self.request((id, request), |transport, (id, request)| {
+            transport.send(*id, request.clone())
+        })
+    }
+}
+
+impl<T: BatchTransport> BatchTransport for Balanced<T> {
+    type Batch = BalancedFuture<T, Vec<(RequestId, rpc::Call)>, T::Batch>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        self.request(requests.into_iter().collect(), |transport, requests| {
+            transport.send_batch(requests.clone())
+        })
+    }
+}
+
+/// A request sent to the endpoints of `Balanced` transport.
+pub struct BalancedFuture<T: Transport, R, F> {
+    endpoints: Arc<Endpoints<T>>,
+    request: R,
+    send: fn(&T, &R) -> F,
+    remaining: std::vec::IntoIter<usize>,
+    current: Option<(usize, Instant, Pin<Box<F>>)>,
+    last_error: Option<Error>,
+}
+
+impl<T, R, F, O> Future for BalancedFuture<T, R, F>
+where
+    T: Transport,
+    R: Unpin,
+    F: Future<Output = error::Result<O>>,
+{
+    type Output = error::Result<O>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let endpoints = &this.endpoints;
+
+        endpoints.poll_probes(cx);
+
+        loop {
+            if let Some((idx, started, ref mut future)) = this.current {
+                let result = ready!(future.as_mut().poll(cx));
+                this.current = None;
+                match result {
+                    Err(err) if is_endpoint_failure(&err) => {
+                        log::debug!("Request to endpoint {} failed: {:?}", idx, err);
+                        endpoints.failed(idx);
+                        this.last_error = Some(err);
+                    }
+                    result => {
+                        endpoints.succeeded(idx, started.elapsed());
+                        return Poll::Ready(result);
+                    }
+                }
+            }
+
+            match this.remaining.next() {
+                Some(idx) => {
+                    let future = (this.send)(&endpoints.endpoints[idx].transport, &this.request);
+                    this.current = Some((idx, Instant::now(), Box::pin(future)));
+                }
+                None => {
+                    let err = this
+                        .last_error
+                        .take()
+                        .unwrap_or_else(|| Error::Transport("No endpoints available.".into()));
+                    return Poll::Ready(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Balanced, HealthPolicy, Strategy};
+    use crate::error::{self, Error};
+    use crate::rpc;
+    use crate::{RequestId, Transport};
+    use futures::channel::oneshot;
+    use futures::executor::block_on;
+    use futures::future::{self, FutureExt, LocalBoxFuture};
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[derive(Debug, Default, Clone)]
+    struct Node {
+        name: u64,
+        down: Rc<Cell<bool>>,
+        delayed: Rc<Cell<bool>>,
+        held: Rc<RefCell<Vec<oneshot::Sender<()>>>>,
+        requests: Rc<RefCell<Vec<(RequestId, String)>>>,
+    }
+
+    impl Node {
+        fn new(name: u64) -> Self {
+            Node {
+                name,
+                ..Default::default()
+            }
+        }
+
+        /// Lets the delayed responses through.
+        fn release(&self) {
+            for sender in self.held.borrow_mut().drain(..) {
+                let _ = sender.send(());
+            }
+        }
+
+        fn methods(&self) -> Vec<String> {
+            self.requests
+                .borrow()
+                .iter()
+                .map(|(_, method)| method.clone())
+                .collect()
+        }
+    }
+
+    impl Transport for Node {
+        type Out = LocalBoxFuture<'static, error::Result<rpc::Value>>;
+
+        fn prepare(&self, _method: &str, _params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+            unreachable!("Requests are prepared by the balanced transport.")
+        }
+
+        fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+            let method = match request {
+                rpc::Call::MethodCall(call) => call.method,
+                _ => unreachable!(),
+            };
+            self.requests.borrow_mut().push((id, method));
+            let result = if self.down.get() {
+                Err(Error::Unreachable)
+            } else {
+                Ok(self.name.into())
+            };
+            if !self.delayed.get() {
+                return future::ready(result).boxed_local();
+            }
+            let (sender, receiver) = oneshot::channel();
+            self.held.borrow_mut().push(sender);
+            receiver.map(move |_| result).boxed_local()
+        }
+    }
+
+    fn policy() -> HealthPolicy {
+        HealthPolicy {
+            failure_threshold: 1,
+            probe_interval: Duration::from_secs(0),
+        }
+    }
+
+    #[test]
+    fn should_fail_over_to_backup() {
+        let (primary, backup) = (Node::new(1), Node::new(2));
+        let transport = Balanced::with_health_policy(
+            vec![primary.clone(), backup.clone()],
+            Strategy::Failover,
+            HealthPolicy {
+                probe_interval: Duration::from_secs(60),
+                ..policy()
+            },
+        );
+        primary.down.set(true);
+
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Ok(2.into()));
+        assert!(!transport.is_healthy(0));
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Ok(2.into()));
+
+        assert_eq!(primary.methods(), vec!["eth_call"]);
+        assert_eq!(backup.methods(), vec!["eth_call", "eth_call"]);
+    }
+
+    #[test]
+    fn should_probe_unhealthy_endpoints() {
+        let (primary, backup) = (Node::new(1), Node::new(2));
+        let transport = Balanced::with_health_policy(vec![primary.clone(), backup], Strategy::Failover, policy());
+        primary.down.set(true);
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Ok(2.into()));
+        assert!(!transport.is_healthy(0));
+
+        primary.down.set(false);
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Ok(2.into()));
+        assert!(transport.is_healthy(0));
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Ok(1.into()));
+
+        assert_eq!(primary.methods(), vec!["eth_call", "eth_blockNumber", "eth_call"]);
+    }
+
+    #[test]
+    fn should_complete_probes_after_the_request() {
+        let (primary, backup) = (Node::new(1), Node::new(2));
+        let transport = Balanced::with_health_policy(vec![primary.clone(), backup], Strategy::Failover, policy());
+        primary.down.set(true);
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Ok(2.into()));
+        assert!(!transport.is_healthy(0));
+
+        // the probe is still in flight when the request completes
+        primary.down.set(false);
+        primary.delayed.set(true);
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Ok(2.into()));
+        assert!(!transport.is_healthy(0));
+
+        // and its result is picked up by the next request
+        primary.release();
+        primary.delayed.set(false);
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Ok(2.into()));
+        assert!(transport.is_healthy(0));
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Ok(1.into()));
+
+        assert_eq!(primary.methods(), vec!["eth_call", "eth_blockNumber", "eth_call"]);
+    }
+
+    #[test]
+    fn should_rotate_endpoints() {
+        let nodes = vec![Node::new(1), Node::new(2), Node::new(3)];
+        let transport = Balanced::new(nodes, Strategy::RoundRobin);
+
+        let results: Vec<_> = (0..4)
+            .map(|_| block_on(transport.execute("eth_call", vec![])).unwrap().as_u64())
+            .collect();
+
+        assert_eq!(results, vec![Some(1), Some(2), Some(3), Some(1)]);
+    }
+
+    #[test]
+    fn should_prefer_lowest_latency() {
+        let transport = Balanced::new(vec![Node::new(1), Node::new(2)], Strategy::LowestLatency);
+        transport.endpoints.succeeded(0, Duration::from_millis(20));
+        transport.endpoints.succeeded(1, Duration::from_millis(10));
+
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Ok(2.into()));
+    }
+
+    #[test]
+    fn should_keep_request_ids_across_endpoints() {
+        let (primary, backup) = (Node::new(1), Node::new(2));
+        let transport = Balanced::new(vec![primary.clone(), backup.clone()], Strategy::Failover);
+        primary.down.set(true);
+
+        let (id, request) = transport.prepare("eth_call", vec![]);
+        assert_eq!(block_on(transport.send(id, request)), Ok(2.into()));
+
+        assert_eq!(primary.requests.borrow()[0].0, id);
+        assert_eq!(backup.requests.borrow()[0].0, id);
+    }
+
+    #[test]
+    fn should_return_last_error_when_all_endpoints_fail() {
+        let node = Node::new(1);
+        node.down.set(true);
+        let transport = Balanced::new(vec![node], Strategy::Failover);
+
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Err(Error::Unreachable));
+    }
+}
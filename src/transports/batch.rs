@@ -5,14 +5,16 @@ use crate::rpc;
 use crate::{BatchTransport, RequestId, Transport};
 use futures::channel::oneshot;
 use futures::{
-    task::{Context, Poll},
+    task::{self, ArcWake, Context, Poll, Waker},
     Future, FutureExt,
 };
+use futures_timer::Delay;
 use parking_lot::Mutex;
 use std::collections::BTreeMap;
 use std::mem;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 type Pending = oneshot::Sender<error::Result<rpc::Value>>;
 type PendingRequests = Arc<Mutex<BTreeMap<RequestId, Pending>>>;
@@ -124,6 +126,222 @@ where
     }
 }
 
+/// Limits of a batch collected by the `AutoBatch` transport.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchWindow {
+    /// Number of requests after which the batch is sent right away.
+    pub max_size: usize,
+    /// Time after the first request of the batch at which the batch is sent.
+    pub max_delay: Duration,
+}
+
+impl Default for BatchWindow {
+    fn default() -> Self {
+        BatchWindow {
+            max_size: 100,
+            max_delay: Duration::from_millis(10),
+        }
+    }
+}
+
+enum WindowState<T: BatchTransport> {
+    Collecting(Vec<(RequestId, rpc::Call)>, Vec<Pending>),
+    Sent(Pin<Box<T::Batch>>, Vec<Pending>),
+    Done,
+}
+
+/// Wakes all requests waiting for the batch, so that it is polled as long as any of them is alive.
+#[derive(Default)]
+struct Waiters(Mutex<BTreeMap<usize, Waker>>);
+
+impl ArcWake for Waiters {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        for waker in arc_self.0.lock().values() {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+/// Requests collected into a single batch.
+struct Window<T: BatchTransport> {
+    transport: T,
+    deadline: Instant,
+    state: Mutex<WindowState<T>>,
+    waiters: Arc<Waiters>,
+}
+
+impl<T: BatchTransport> Window<T> {
+    /// Sends the batch unless it was sent already.
+    fn submit(&self) {
+        let mut state = self.state.lock();
+        if let WindowState::Collecting(ref mut requests, ref mut pending) = *state {
+            let batch = self.transport.send_batch(mem::take(requests));
+            *state = WindowState::Sent(Box::pin(batch), mem::take(pending));
+        }
+    }
+
+    /// Polls the sent batch on behalf of the request at given index and hands out the results
+    /// once it completes.
+    fn poll_batch(&self, index: usize, ctx: &mut Context) -> Poll<()> {
+        let mut state = self.state.lock();
+        let res = match *state {
+            WindowState::Sent(ref mut batch, _) => {
+                self.waiters.0.lock().insert(index, ctx.waker().clone());
+                let waker = task::waker(self.waiters.clone());
+                ready!(batch.as_mut().poll(&mut Context::from_waker(&waker)))
+            }
+            _ => return Poll::Ready(()),
+        };
+
+        if let WindowState::Sent(_, pending) = mem::replace(&mut *state, WindowState::Done) {
+            for (idx, tx) in pending.into_iter().enumerate() {
+                // Ignore sending error
+                let _ = match res {
+                    Ok(ref results) if results.len() > idx => tx.send(results[idx].clone()),
+                    Err(ref err) => tx.send(Err(err.clone())),
+                    _ => tx.send(Err(Error::Internal)),
+                };
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
+/// Transport sending queries as batches without explicit submission.
+///
+/// Requests are collected until the batch reaches `BatchWindow::max_size` or `BatchWindow::max_delay`
+/// passes since the first of them. The batch is sent by whichever of the requests is polled first.
+pub struct AutoBatch<T: BatchTransport> {
+    transport: T,
+    limits: BatchWindow,
+    window: Arc<Mutex<Option<Arc<Window<T>>>>>,
+}
+
+impl<T: BatchTransport> Clone for AutoBatch<T> {
+    fn clone(&self) -> Self {
+        AutoBatch {
+            transport: self.transport.clone(),
+            limits: self.limits.clone(),
+            window: self.window.clone(),
+        }
+    }
+}
+
+impl<T: BatchTransport> std::fmt::Debug for AutoBatch<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AutoBatch")
+            .field("transport", &self.transport)
+            .field("limits", &self.limits)
+            .finish()
+    }
+}
+
+impl<T: BatchTransport> AutoBatch<T> {
+    /// Creates new AutoBatch transport given existing transport supporting batch requests.
+    pub fn new(transport: T, limits: BatchWindow) -> Self {
+        AutoBatch {
+            transport,
+            limits,
+            window: Default::default(),
+        }
+    }
+}
+
+impl<T: BatchTransport> Transport for AutoBatch<T> {
+    type Out = AutoBatchResult<T>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        self.transport.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        let mut current = self.window.lock();
+        let window = match *current {
+            Some(ref window) if matches!(*window.state.lock(), WindowState::Collecting(..)) => window.clone(),
+            _ => {
+                let window = Arc::new(Window {
+                    transport: self.transport.clone(),
+                    deadline: Instant::now() + self.limits.max_delay,
+                    state: Mutex::new(WindowState::Collecting(vec![], vec![])),
+                    waiters: Default::default(),
+                });
+                *current = Some(window.clone());
+                window
+            }
+        };
+
+        let (tx, rx) = oneshot::channel();
+        let (index, full) = match *window.state.lock() {
+            WindowState::Collecting(ref mut requests, ref mut pending) => {
+                requests.push((id, request));
+                pending.push(tx);
+                (requests.len() - 1, requests.len() >= self.limits.max_size)
+            }
+            _ => unreachable!("Only collecting windows are current."),
+        };
+        if full {
+            *current = None;
+            window.submit();
+        }
+
+        AutoBatchResult {
+            window,
+            index,
+            delay: None,
+            response: rx,
+        }
+    }
+}
+
+impl<T: BatchTransport> BatchTransport for AutoBatch<T> {
+    type Batch = T::Batch;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        self.transport.send_batch(requests)
+    }
+}
+
+/// Result of calling a single method through the `AutoBatch` transport.
+pub struct AutoBatchResult<T: BatchTransport> {
+    window: Arc<Window<T>>,
+    index: usize,
+    delay: Option<Delay>,
+    response: oneshot::Receiver<error::Result<rpc::Value>>,
+}
+
+impl<T: BatchTransport> Future for AutoBatchResult<T> {
+    type Output = error::Result<rpc::Value>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Poll::Ready(result) = this.response.poll_unpin(ctx) {
+            return Poll::Ready(result.map_err(|_| Error::Internal)?);
+        }
+
+        if matches!(*this.window.state.lock(), WindowState::Collecting(..)) {
+            let deadline = this.window.deadline;
+            let delay = this
+                .delay
+                .get_or_insert_with(|| Delay::new(deadline.saturating_duration_since(Instant::now())));
+            ready!(delay.poll_unpin(ctx));
+            this.window.submit();
+        }
+
+        // Once the batch completes, the results are handed out to all requests.
+        ready!(this.window.poll_batch(this.index, ctx));
+        Poll::Ready(ready!(this.response.poll_unpin(ctx)).map_err(|_| Error::Internal)?)
+    }
+}
+
+impl<T: BatchTransport> Drop for AutoBatchResult<T> {
+    fn drop(&mut self) {
+        self.window.waiters.0.lock().remove(&self.index);
+    }
+}
+
 /// Result of calling a single method that will be part of the batch.
 /// Converts `oneshot::Receiver` error into `Error::Internal`
 pub struct SingleResult(oneshot::Receiver<error::Result<rpc::Value>>);
@@ -135,3 +353,172 @@ impl Future for SingleResult {
         Poll::Ready(ready!(self.0.poll_unpin(ctx)).map_err(|_| Error::Internal)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AutoBatch, BatchWindow};
+    use crate::error::{self, Error};
+    use crate::rpc;
+    use crate::{BatchTransport, RequestId, Transport};
+    use futures::channel::oneshot;
+    use futures::executor::block_on;
+    use futures::task::{self, ArcWake, Context, Poll};
+    use futures::{future, FutureExt};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[derive(Debug, Clone, Default)]
+    struct Node(Rc<RefCell<Vec<usize>>>);
+
+    impl Transport for Node {
+        type Out = future::Ready<error::Result<rpc::Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+            (1, crate::helpers::build_request(1, method, params))
+        }
+
+        fn send(&self, _id: RequestId, _request: rpc::Call) -> Self::Out {
+            unreachable!("Requests are sent in batches.")
+        }
+    }
+
+    impl BatchTransport for Node {
+        type Batch = future::Ready<error::Result<Vec<error::Result<rpc::Value>>>>;
+
+        fn send_batch<I>(&self, requests: I) -> Self::Batch
+        where
+            I: IntoIterator<Item = (RequestId, rpc::Call)>,
+        {
+            let results: Vec<_> = requests
+                .into_iter()
+                .map(|(_, request)| match request {
+                    rpc::Call::MethodCall(call) if call.method == "eth_fail" => Err(Error::Unreachable),
+                    rpc::Call::MethodCall(call) => Ok(call.method.into()),
+                    _ => Err(Error::Internal),
+                })
+                .collect();
+            self.0.borrow_mut().push(results.len());
+            future::ready(Ok(results))
+        }
+    }
+
+    #[test]
+    fn should_send_batch_when_full() {
+        let node = Node::default();
+        let transport = AutoBatch::new(
+            node.clone(),
+            BatchWindow {
+                max_size: 2,
+                max_delay: Duration::from_secs(3600),
+            },
+        );
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        let first = transport.execute("eth_blockNumber", vec![]);
+        let second = transport.execute("eth_fail", vec![]);
+        let mut third = transport.execute("eth_chainId", vec![]);
+
+        assert_eq!(block_on(second), Err(Error::Unreachable));
+        assert_eq!(block_on(first), Ok("eth_blockNumber".into()));
+        assert!(third.poll_unpin(&mut cx).is_pending());
+        assert_eq!(*node.0.borrow(), vec![2]);
+    }
+
+    #[test]
+    fn should_send_batch_after_delay() {
+        let node = Node::default();
+        let transport = AutoBatch::new(
+            node.clone(),
+            BatchWindow {
+                max_size: 10,
+                max_delay: Duration::from_millis(50),
+            },
+        );
+
+        let first = transport.execute("eth_blockNumber", vec![]);
+        let second = transport.execute("eth_chainId", vec![]);
+
+        assert_eq!(
+            block_on(future::join(first, second)),
+            (Ok("eth_blockNumber".into()), Ok("eth_chainId".into()))
+        );
+        assert_eq!(*node.0.borrow(), vec![2]);
+    }
+
+    /// Node answering batches only once released.
+    #[derive(Debug, Clone, Default)]
+    struct GatedNode(Rc<RefCell<Vec<oneshot::Sender<()>>>>);
+
+    impl Transport for GatedNode {
+        type Out = future::Ready<error::Result<rpc::Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+            (1, crate::helpers::build_request(1, method, params))
+        }
+
+        fn send(&self, _id: RequestId, _request: rpc::Call) -> Self::Out {
+            unreachable!("Requests are sent in batches.")
+        }
+    }
+
+    impl BatchTransport for GatedNode {
+        type Batch = future::LocalBoxFuture<'static, error::Result<Vec<error::Result<rpc::Value>>>>;
+
+        fn send_batch<I>(&self, requests: I) -> Self::Batch
+        where
+            I: IntoIterator<Item = (RequestId, rpc::Call)>,
+        {
+            let results: Vec<_> = requests
+                .into_iter()
+                .map(|(_, request)| match request {
+                    rpc::Call::MethodCall(call) => Ok(call.method.into()),
+                    _ => Err(Error::Internal),
+                })
+                .collect();
+            let (tx, rx) = oneshot::channel();
+            self.0.borrow_mut().push(tx);
+            rx.map(move |_| Ok(results)).boxed_local()
+        }
+    }
+
+    #[derive(Default)]
+    struct Woken(AtomicBool);
+
+    impl ArcWake for Woken {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn should_deliver_results_after_polling_request_is_dropped() {
+        let node = GatedNode::default();
+        let transport = AutoBatch::new(
+            node.clone(),
+            BatchWindow {
+                max_size: 2,
+                max_delay: Duration::from_secs(3600),
+            },
+        );
+        let woken = Arc::new(Woken::default());
+        let waker = task::waker(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut noop_cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        let mut first = transport.execute("eth_blockNumber", vec![]);
+        let mut second = transport.execute("eth_chainId", vec![]);
+        assert!(second.poll_unpin(&mut cx).is_pending());
+        assert!(first.poll_unpin(&mut noop_cx).is_pending());
+        drop(first);
+
+        for tx in node.0.borrow_mut().drain(..) {
+            tx.send(()).unwrap();
+        }
+
+        assert!(woken.0.load(Ordering::SeqCst));
+        assert_eq!(second.poll_unpin(&mut cx), Poll::Ready(Ok("eth_chainId".into())));
+    }
+}
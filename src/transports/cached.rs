@@ -0,0 +1,398 @@
+//! Caching Transport
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::error;
+use crate::rpc;
+use crate::{BatchTransport, RequestId, Transport};
+use futures::future::Shared;
+use futures::task::{Context, Poll};
+use futures::{Future, FutureExt};
+use parking_lot::Mutex;
+
+/// Condition under which a response may be cached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rule {
+    /// The response never changes.
+    Always,
+    /// The response never changes once it's known (e.g. a block by hash).
+    NotNull,
+    /// The response never changes once the transaction is mined.
+    Mined,
+    /// The response never changes if the block (param at given index) is given by number or hash.
+    AtBlock(usize),
+}
+
+impl Rule {
+    fn for_method(method: &str) -> Option<Self> {
+        Some(match method {
+            "eth_chainId" | "net_version" => Rule::Always,
+            "eth_getBlockByHash" | "eth_getTransactionReceipt" => Rule::NotNull,
+            "eth_getTransactionByHash" => Rule::Mined,
+            "eth_call" | "eth_getBalance" | "eth_getCode" | "eth_getTransactionCount" => Rule::AtBlock(1),
+            "eth_getStorageAt" => Rule::AtBlock(2),
+            _ => return None,
+        })
+    }
+
+    fn accepts_request(self, params: &rpc::Params) -> bool {
+        match self {
+            Rule::AtBlock(idx) => match params {
+                rpc::Params::Array(params) => matches!(params.get(idx), Some(param) if is_fixed_block(param)),
+                _ => false,
+            },
+            _ => true,
+        }
+    }
+
+    fn accepts_response(self, value: &rpc::Value) -> bool {
+        match self {
+            Rule::Always | Rule::AtBlock(_) => true,
+            Rule::NotNull => !value.is_null(),
+            Rule::Mined => matches!(value.get("blockHash"), Some(hash) if !hash.is_null()),
+        }
+    }
+}
+
+/// Returns true if the block parameter refers to a specific block (not e.g. `latest` or `pending`).
+fn is_fixed_block(block: &rpc::Value) -> bool {
+    match block {
+        rpc::Value::String(block) => block.starts_with("0x") || block == "earliest",
+        // EIP-1898 block specification.
+        rpc::Value::Object(block) => {
+            let number = block.get("blockNumber").and_then(rpc::Value::as_str);
+            block.contains_key("blockHash") || matches!(number, Some(number) if number.starts_with("0x"))
+        }
+        _ => false,
+    }
+}
+
+/// Least-recently used responses.
+#[derive(Debug)]
+struct Lru {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<String, (rpc::Value, u64)>,
+    order: BTreeMap<u64, String>,
+}
+
+impl Lru {
+    fn get(&mut self, key: &str) -> Option<rpc::Value> {
+        let tick = self.tick + 1;
+        let (value, used) = self.entries.get_mut(key)?;
+        self.order.remove(used);
+        self.order.insert(tick, key.into());
+        *used = tick;
+        self.tick = tick;
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: String, value: rpc::Value) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.insert(key.clone(), (value, self.tick)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(self.tick, key);
+        while self.entries.len() > self.capacity {
+            let oldest = *self
+                .order
+                .keys()
+                .next()
+                .expect("order has an entry for every cached value; qed");
+            let key = self.order.remove(&oldest).expect("key was found above; qed");
+            self.entries.remove(&key);
+        }
+    }
+}
+
+struct CacheState<F: Future> {
+    responses: Lru,
+    in_flight: HashMap<String, Shared<F>>,
+}
+
+/// Transport caching responses which can't change, e.g. blocks by hash or calls at a given block number.
+///
+/// Calls against `latest`, `pending` or other moving blocks are never cached.
+/// Identical cacheable requests sent while one of them is awaiting response are sent to the inner transport once.
+pub struct Cached<T: Transport> {
+    transport: T,
+    state: Arc<Mutex<CacheState<T::Out>>>,
+}
+
+impl<T: Transport> Clone for Cached<T> {
+    fn clone(&self) -> Self {
+        Cached {
+            transport: self.transport.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T: Transport> fmt::Debug for Cached<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock();
+        fmt.debug_struct("Cached")
+            .field("transport", &self.transport)
+            .field("capacity", &state.responses.capacity)
+            .field("cached", &state.responses.entries.len())
+            .field("in_flight", &state.in_flight.len())
+            .finish()
+    }
+}
+
+impl<T: Transport> Cached<T> {
+    /// Wraps given transport, keeping up to `capacity` responses.
+    pub fn new(transport: T, capacity: usize) -> Self {
+        Cached {
+            transport,
+            state: Arc::new(Mutex::new(CacheState {
+                responses: Lru {
+                    capacity,
+                    tick: 0,
+                    entries: Default::default(),
+                    order: Default::default(),
+                },
+                in_flight: Default::default(),
+            })),
+        }
+    }
+
+    /// Returns a reference to the inner transport.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+}
+
+impl<T: Transport> Transport for Cached<T> {
+    type Out = CachedFuture<T::Out>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        self.transport.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        let cacheable = match request {
+            rpc::Call::MethodCall(ref call) => Rule::for_method(&call.method)
+                .filter(|rule| rule.accepts_request(&call.params))
+                .map(|rule| (rule, cache_key(&call.method, &call.params))),
+            _ => None,
+        };
+        let (rule, key) = match cacheable {
+            Some(cacheable) => cacheable,
+            None => return CachedFuture(CachedState::Uncached(self.transport.send(id, request))),
+        };
+
+        let mut state = self.state.lock();
+        if let Some(value) = state.responses.get(&key) {
+            return CachedFuture(CachedState::Cached(Some(value)));
+        }
+        let future = state
+            .in_flight
+            .entry(key.clone())
+            .or_insert_with(|| self.transport.send(id, request).shared())
+            .clone();
+        CachedFuture(CachedState::Shared {
+            key,
+            rule,
+            future,
+            state: self.state.clone(),
+        })
+    }
+}
+
+fn cache_key(method: &str, params: &rpc::Params) -> String {
+    let params = serde_json::to_string(params).expect("Params are always serializable; qed");
+    format!("{}:{}", method, params)
+}
+
+impl<T: BatchTransport> BatchTransport for Cached<T> {
+    type Batch = T::Batch;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        self.transport.send_batch(requests)
+    }
+}
+
+enum CachedState<F: Future> {
+    /// Response found in the cache.
+    Cached(Option<rpc::Value>),
+    /// Request which can't be cached.
+    Uncached(F),
+    /// Request shared by identical ones sent concurrently.
+    Shared {
+        key: String,
+        rule: Rule,
+        future: Shared<F>,
+        state: Arc<Mutex<CacheState<F>>>,
+    },
+}
+
+/// A response of `Cached` transport.
+pub struct CachedFuture<F: Future>(CachedState<F>);
+
+impl<F> Future for CachedFuture<F>
+where
+    F: Future<Output = error::Result<rpc::Value>> + Unpin,
+{
+    type Output = error::Result<rpc::Value>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match self.0 {
+            CachedState::Cached(ref mut value) => Poll::Ready(Ok(value.take().expect("Poll after Ready."))),
+            CachedState::Uncached(ref mut future) => future.poll_unpin(cx),
+            CachedState::Shared {
+                ref key,
+                rule,
+                ref mut future,
+                ref state,
+            } => {
+                let result = ready!(future.poll_unpin(cx));
+                let mut state = state.lock();
+                state.in_flight.remove(key);
+                if let Ok(ref value) = result {
+                    if rule.accepts_response(value) {
+                        state.responses.insert(key.clone(), value.clone());
+                    }
+                }
+                Poll::Ready(result)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cached;
+    use crate::error::{self, Error};
+    use crate::rpc;
+    use crate::{RequestId, Transport};
+    use futures::channel::oneshot;
+    use futures::executor::block_on;
+    use futures::{future, FutureExt};
+    use serde_json::json;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Pending = (String, oneshot::Sender<error::Result<rpc::Value>>);
+
+    #[derive(Debug, Clone, Default)]
+    struct Node(Rc<RefCell<Vec<Pending>>>);
+
+    impl Node {
+        fn reply(&self, value: rpc::Value) -> String {
+            let (method, sender) = self.0.borrow_mut().remove(0);
+            sender.send(Ok(value)).unwrap();
+            method
+        }
+    }
+
+    impl Transport for Node {
+        type Out = future::BoxFuture<'static, error::Result<rpc::Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+            (1, crate::helpers::build_request(1, method, params))
+        }
+
+        fn send(&self, _id: RequestId, request: rpc::Call) -> Self::Out {
+            let method = match request {
+                rpc::Call::MethodCall(call) => call.method,
+                _ => unreachable!(),
+            };
+            let (tx, rx) = oneshot::channel();
+            self.0.borrow_mut().push((method, tx));
+            rx.map(|result| result.unwrap_or(Err(Error::Internal))).boxed()
+        }
+    }
+
+    #[test]
+    fn should_cache_calls_at_fixed_block() {
+        let node = Node::default();
+        let transport = Cached::new(node.clone(), 10);
+        let call = || transport.execute("eth_call", vec![json!({"to": "0x00"}), json!("0x10")]);
+
+        let response = call();
+        node.reply(json!("0x01"));
+        assert_eq!(block_on(response), Ok(json!("0x01")));
+        assert_eq!(block_on(call()), Ok(json!("0x01")));
+        assert!(node.0.borrow().is_empty());
+    }
+
+    #[test]
+    fn should_not_cache_calls_at_latest_block() {
+        let node = Node::default();
+        let transport = Cached::new(node.clone(), 10);
+
+        for block in &["latest", "pending"] {
+            for _ in 0..2 {
+                let response = transport.execute("eth_call", vec![json!({"to": "0x00"}), json!(block)]);
+                node.reply(json!("0x01"));
+                assert_eq!(block_on(response), Ok(json!("0x01")));
+            }
+        }
+        let response = transport.execute("eth_getBalance", vec![json!("0x00")]);
+        node.reply(json!("0x01"));
+        assert_eq!(block_on(response), Ok(json!("0x01")));
+        assert!(node.0.borrow().is_empty());
+    }
+
+    #[test]
+    fn should_not_cache_pending_transactions() {
+        let node = Node::default();
+        let transport = Cached::new(node.clone(), 10);
+        let receipt = || transport.execute("eth_getTransactionReceipt", vec![json!("0x01")]);
+
+        let response = receipt();
+        node.reply(rpc::Value::Null);
+        assert_eq!(block_on(response), Ok(rpc::Value::Null));
+
+        let response = receipt();
+        node.reply(json!({"blockHash": "0x02"}));
+        assert_eq!(block_on(response), Ok(json!({"blockHash": "0x02"})));
+        assert_eq!(block_on(receipt()), Ok(json!({"blockHash": "0x02"})));
+    }
+
+    #[test]
+    fn should_coalesce_concurrent_requests() {
+        let node = Node::default();
+        let transport = Cached::new(node.clone(), 10);
+
+        let first = transport.execute("eth_chainId", vec![]);
+        let second = transport.execute("eth_chainId", vec![]);
+        assert_eq!(node.0.borrow().len(), 1);
+
+        node.reply(json!("0x1"));
+        assert_eq!(block_on(second), Ok(json!("0x1")));
+        assert_eq!(block_on(first), Ok(json!("0x1")));
+    }
+
+    #[test]
+    fn should_evict_least_recently_used() {
+        let node = Node::default();
+        let transport = Cached::new(node.clone(), 2);
+        let block = |hash: &str| transport.execute("eth_getBlockByHash", vec![json!(hash), json!(false)]);
+
+        for hash in &["0x1", "0x2"] {
+            let response = block(hash);
+            node.reply(json!({ "hash": hash }));
+            block_on(response).unwrap();
+        }
+        block_on(block("0x1")).unwrap();
+        let response = block("0x3");
+        node.reply(json!({"hash": "0x3"}));
+        block_on(response).unwrap();
+
+        block_on(block("0x1")).unwrap();
+        let response = block("0x2");
+        assert_eq!(node.reply(json!({"hash": "0x2"})), "eth_getBlockByHash");
+        block_on(response).unwrap();
+    }
+}
@@ -0,0 +1,343 @@
+//! In-process Transport
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::pin::Pin;
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::Arc;
+
+use crate::api::SubscriptionId;
+use crate::error::{self, Error};
+use crate::helpers;
+use crate::rpc;
+use crate::{BatchTransport, DuplexTransport, RequestId, Transport};
+use futures::compat::Future01CompatExt;
+use futures::future::BoxFuture;
+use futures::task::{self, ArcWake, Context, Poll, Waker};
+use futures::{Future, FutureExt, Stream, StreamExt};
+use parking_lot::Mutex;
+
+/// Capacity of the channel for notifications sent by the publish-subscribe handler.
+#[cfg(feature = "in-process-pubsub")]
+const NOTIFICATIONS_BUFFER: usize = 1024;
+
+/// Transport calling a `jsonrpc_core` handler directly, without any I/O.
+///
+/// Useful for talking to a node (or a chain simulator) embedded in the same process.
+#[derive(Clone)]
+pub struct InProcess<M: rpc::Metadata = ()> {
+    id: Arc<AtomicUsize>,
+    io: Arc<rpc::MetaIoHandler<M>>,
+    meta: M,
+    router: Option<Arc<Router>>,
+}
+
+impl<M: rpc::Metadata> fmt::Debug for InProcess<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InProcess")
+            .field("id", &self.id)
+            .field("methods", &self.io.iter().map(|(name, _)| name).collect::<Vec<_>>())
+            .field("pubsub", &self.router.is_some())
+            .finish()
+    }
+}
+
+impl InProcess {
+    /// Creates new transport calling given handler.
+    pub fn new(io: rpc::IoHandler) -> Self {
+        Self::with_metadata(io.into(), ())
+    }
+}
+
+impl<M: rpc::Metadata> InProcess<M> {
+    /// Creates new transport calling given handler with `meta` passed to every call.
+    pub fn with_metadata(io: rpc::MetaIoHandler<M>, meta: M) -> Self {
+        InProcess {
+            id: Arc::new(AtomicUsize::new(1)),
+            io: Arc::new(io),
+            meta,
+            router: None,
+        }
+    }
+
+    fn handle(&self, request: rpc::Request) -> impl Future<Output = error::Result<Option<rpc::Response>>> {
+        self.io
+            .handle_rpc_request(request, self.meta.clone())
+            .compat()
+            .map(|response| response.map_err(|()| Error::Internal))
+    }
+}
+
+#[cfg(feature = "in-process-pubsub")]
+impl InProcess<Arc<jsonrpc_pubsub::Session>> {
+    /// Creates new transport calling given publish-subscribe handler.
+    ///
+    /// All calls share a single session, and notifications sent to it are delivered to the subscriptions
+    /// of the transport.
+    pub fn with_pubsub(handler: jsonrpc_pubsub::PubSubHandler<Arc<jsonrpc_pubsub::Session>>) -> Self {
+        use futures::compat::Stream01CompatExt;
+
+        let (sender, notifications) = rpc::futures::sync::mpsc::channel(NOTIFICATIONS_BUFFER);
+        let notifications = notifications
+            .compat()
+            .filter_map(|message| futures::future::ready(message.ok()));
+        let mut transport = Self::with_metadata(handler.into(), Arc::new(jsonrpc_pubsub::Session::new(sender)));
+        transport.router = Some(Arc::new(Router::new(notifications.boxed())));
+        transport
+    }
+}
+
+impl<M: rpc::Metadata + Unpin> Transport for InProcess<M> {
+    type Out = BoxFuture<'static, error::Result<rpc::Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        let id = self.id.fetch_add(1, atomic::Ordering::AcqRel);
+        let request = helpers::build_request(id, method, params);
+
+        (id, request)
+    }
+
+    fn send(&self, _id: RequestId, request: rpc::Call) -> Self::Out {
+        self.handle(rpc::Request::Single(request))
+            .map(|response| match response? {
+                Some(rpc::Response::Single(output)) => helpers::to_result_from_output(output),
+                response => Err(Error::InvalidResponse(format!(
+                    "Expected single response, got: {:?}",
+                    response
+                ))),
+            })
+            .boxed()
+    }
+}
+
+impl<M: rpc::Metadata + Unpin> BatchTransport for InProcess<M> {
+    type Batch = BoxFuture<'static, error::Result<Vec<error::Result<rpc::Value>>>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        let requests = requests.into_iter().map(|(_, call)| call).collect();
+        self.handle(rpc::Request::Batch(requests))
+            .map(|response| match response? {
+                Some(rpc::Response::Batch(outputs)) => helpers::to_results_from_outputs(outputs),
+                response => Err(Error::InvalidResponse(format!(
+                    "Expected batch response, got: {:?}",
+                    response
+                ))),
+            })
+            .boxed()
+    }
+}
+
+impl<M: rpc::Metadata + Unpin> DuplexTransport for InProcess<M> {
+    type NotificationStream = NotificationStream;
+
+    fn subscribe(&self, id: SubscriptionId) -> error::Result<Self::NotificationStream> {
+        let router = self
+            .router
+            .clone()
+            .ok_or_else(|| Error::Transport("Subscriptions require a publish-subscribe handler.".into()))?;
+        router.state.lock().subscriptions.insert(id.clone(), VecDeque::new());
+        Ok(NotificationStream { id, router })
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> error::Result {
+        if let Some(ref router) = self.router {
+            router.state.lock().subscriptions.remove(&id);
+            if let Some(waker) = router.wakers.0.lock().remove(&id) {
+                waker.wake();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wakes every subscription when the handler sends a notification.
+#[derive(Default)]
+struct Wakers(Mutex<BTreeMap<SubscriptionId, Waker>>);
+
+impl ArcWake for Wakers {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        let wakers: Vec<_> = arc_self.0.lock().values().cloned().collect();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+struct RouterState {
+    notifications: Pin<Box<dyn Stream<Item = String> + Send>>,
+    subscriptions: BTreeMap<SubscriptionId, VecDeque<rpc::Value>>,
+}
+
+/// Dispatches notifications to subscriptions.
+///
+/// There is no background task: notifications are received by whichever subscription stream is polled.
+struct Router {
+    state: Mutex<RouterState>,
+    wakers: Arc<Wakers>,
+}
+
+impl Router {
+    #[cfg_attr(not(feature = "in-process-pubsub"), allow(dead_code))]
+    fn new(notifications: Pin<Box<dyn Stream<Item = String> + Send>>) -> Self {
+        Router {
+            state: Mutex::new(RouterState {
+                notifications,
+                subscriptions: Default::default(),
+            }),
+            wakers: Default::default(),
+        }
+    }
+
+    fn poll_notification(&self, id: &SubscriptionId, cx: &mut Context) -> Poll<Option<rpc::Value>> {
+        let mut state = self.state.lock();
+        if !state.subscriptions.contains_key(id) {
+            return Poll::Ready(None);
+        }
+        // Register first, so that notifications arriving while receiving wake this stream.
+        self.wakers.0.lock().insert(id.clone(), cx.waker().clone());
+
+        let waker = task::waker(self.wakers.clone());
+        let mut router_cx = Context::from_waker(&waker);
+        while let Poll::Ready(Some(message)) = state.notifications.poll_next_unpin(&mut router_cx) {
+            match parse_notification(&message) {
+                Some((id, result)) => match state.subscriptions.get_mut(&id) {
+                    Some(queue) => queue.push_back(result),
+                    None => log::warn!("Got notification for unknown subscription (id: {:?})", id),
+                },
+                None => log::error!("Got unsupported notification: {}", message),
+            }
+        }
+
+        match state.subscriptions.get_mut(id).and_then(VecDeque::pop_front) {
+            Some(result) => Poll::Ready(Some(result)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+fn parse_notification(message: &str) -> Option<(SubscriptionId, rpc::Value)> {
+    let notification = helpers::to_notification_from_slice(message.as_bytes()).ok()?;
+    match notification.params {
+        rpc::Params::Map(mut params) => match (params.remove("subscription"), params.remove("result")) {
+            (Some(rpc::Value::String(id)), Some(result)) => Some((id.into(), result)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Stream of notifications of a subscription of `InProcess` transport.
+pub struct NotificationStream {
+    id: SubscriptionId,
+    router: Arc<Router>,
+}
+
+impl fmt::Debug for NotificationStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NotificationStream").field("id", &self.id).finish()
+    }
+}
+
+impl Stream for NotificationStream {
+    type Item = rpc::Value;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.router.poll_notification(&self.id, cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InProcess;
+    use crate::api::Web3;
+    use crate::error::Error;
+    use crate::rpc;
+    use crate::types::U64;
+    use crate::{BatchTransport, DuplexTransport, Transport};
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    fn handler() -> rpc::IoHandler {
+        let mut io = rpc::IoHandler::new();
+        io.add_method("eth_blockNumber", |_| Ok(json!("0x2a")));
+        io.add_method("eth_getBalance", |params: rpc::Params| {
+            let params: Vec<rpc::Value> = params.parse()?;
+            Ok(params[0].clone())
+        });
+        io
+    }
+
+    #[test]
+    fn should_call_handler() {
+        let transport = InProcess::new(handler());
+        let web3 = Web3::new(transport.clone());
+
+        assert_eq!(block_on(web3.eth().block_number()), Ok(U64::from(42)));
+        assert_eq!(
+            block_on(transport.execute("eth_getBalance", vec![json!("0x1")])),
+            Ok(json!("0x1"))
+        );
+        assert_eq!(
+            block_on(transport.execute("eth_chainId", vec![])),
+            Err(Error::Rpc(rpc::Error::method_not_found()))
+        );
+        assert!(transport.subscribe("0x1".to_owned().into()).is_err());
+    }
+
+    #[test]
+    fn should_call_handler_with_batch() {
+        let transport = InProcess::new(handler());
+
+        let requests = vec![
+            transport.prepare("eth_blockNumber", vec![]),
+            transport.prepare("eth_chainId", vec![]),
+        ];
+        let results = block_on(transport.send_batch(requests)).unwrap();
+
+        assert_eq!(results[0], Ok(json!("0x2a")));
+        assert_eq!(results[1], Err(Error::Rpc(rpc::Error::method_not_found())));
+    }
+
+    #[cfg(feature = "in-process-pubsub")]
+    #[test]
+    fn should_route_notifications_to_subscriptions() {
+        use futures::StreamExt;
+        use jsonrpc_pubsub::typed::Subscriber;
+        use jsonrpc_pubsub::{PubSubHandler, Session, SubscriptionId};
+        use std::sync::{Arc, Mutex};
+
+        let sinks = Arc::new(Mutex::new(Vec::new()));
+        let mut handler = PubSubHandler::<Arc<Session>>::default();
+        let subscribed = sinks.clone();
+        handler.add_subscription(
+            "eth_subscription",
+            ("eth_subscribe", move |_, _, subscriber: jsonrpc_pubsub::Subscriber| {
+                let subscriber = Subscriber::<rpc::Value>::new(subscriber);
+                let id = format!("0x{}", subscribed.lock().unwrap().len() + 1);
+                let sink = subscriber.assign_id(SubscriptionId::String(id)).unwrap();
+                subscribed.lock().unwrap().push(sink);
+            }),
+            ("eth_unsubscribe", |_, _| rpc::futures::future::ok(json!(true))),
+        );
+        let transport = InProcess::with_pubsub(handler);
+
+        let first = block_on(transport.execute("eth_subscribe", vec![json!("newHeads")])).unwrap();
+        let second = block_on(transport.execute("eth_subscribe", vec![json!("newHeads")])).unwrap();
+        assert_eq!((first.clone(), second.clone()), (json!("0x1"), json!("0x2")));
+        let mut first = transport.subscribe(first.as_str().unwrap().to_owned().into()).unwrap();
+        let mut second = transport.subscribe(second.as_str().unwrap().to_owned().into()).unwrap();
+
+        use rpc::futures::Future;
+        let sinks = sinks.lock().unwrap();
+        sinks[1].notify(Ok(json!(2))).wait().unwrap();
+        sinks[0].notify(Ok(json!(1))).wait().unwrap();
+
+        assert_eq!(block_on(first.next()), Some(json!(1)));
+        assert_eq!(block_on(second.next()), Some(json!(2)));
+        transport.unsubscribe("0x1".to_owned().into()).unwrap();
+        assert_eq!(block_on(first.next()), None);
+    }
+}
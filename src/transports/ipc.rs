@@ -0,0 +1,460 @@
+//! IPC Transport (Unix domain sockets)
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{atomic, Arc};
+
+use super::shared::{
+    batch_to_batch, batch_to_single, dispatch_message, dropped_err, notification_channel, parse_message, BatchResult,
+    Pending, SingleResult, Subscription, TransportMessage,
+};
+use crate::api::SubscriptionId;
+use crate::error;
+use crate::helpers;
+use crate::rpc;
+use crate::{BatchTransport, DuplexTransport, RequestId, Transport};
+use futures::channel::{mpsc, oneshot};
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, Stream, StreamExt};
+
+pub use super::shared::{NotificationStream, Response};
+
+// The size of a single read from the socket.
+const READ_BUFFER_SIZE: usize = 4096;
+
+struct IpcServerTask<S> {
+    pending: BTreeMap<RequestId, Pending>,
+    subscriptions: BTreeMap<SubscriptionId, Subscription>,
+    stream: S,
+}
+
+impl<S> IpcServerTask<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn new(stream: S) -> Self {
+        Self {
+            pending: Default::default(),
+            subscriptions: Default::default(),
+            stream,
+        }
+    }
+
+    async fn into_task(self, requests: mpsc::UnboundedReceiver<TransportMessage>) {
+        let Self {
+            stream,
+            mut pending,
+            mut subscriptions,
+        } = self;
+
+        let (reader, mut writer) = stream.split();
+        let receiver = as_message_stream(reader).fuse();
+        let requests = requests.fuse();
+        pin_mut!(receiver);
+        pin_mut!(requests);
+        loop {
+            select! {
+                msg = requests.next() => match msg {
+                    Some(TransportMessage::Request { id, request, sender: tx }) => {
+                        if pending.insert(id, tx).is_some() {
+                            log::warn!("Replacing a pending request with id {:?}", id);
+                        }
+                        let request = helpers::to_string(&request);
+                        log::debug!("[{}] Calling: {}", id, request);
+                        let res = writer.write_all(request.as_bytes()).await;
+                        let res2 = writer.flush().await;
+                        if let Err(e) = res.and(res2) {
+                            log::error!("IPC connection error: {:?}", e);
+                            pending.remove(&id);
+                        }
+                    }
+                    Some(TransportMessage::Subscribe { id, sink }) => {
+                        let replaced = subscriptions.insert(id.clone(), sink);
+                        if replaced.is_some() {
+                            log::warn!("Replacing already-registered subscription with id {:?}", id);
+                        }
+                    }
+                    Some(TransportMessage::Unsubscribe { id }) => {
+                        let removed = subscriptions.remove(&id);
+                        if removed.is_none() {
+                            log::warn!("Unsubscribing from non-existent subscription with id {:?}", id);
+                        }
+                    }
+                    None => {}
+                },
+                res = receiver.next() => match res {
+                    Some(Ok(messages)) => {
+                        for message in messages.iter().filter_map(|data| parse_message(data)) {
+                            dispatch_message(message, &subscriptions, &mut pending).await;
+                        }
+                    },
+                    Some(Err(e)) => {
+                        log::error!("IPC connection error: {:?}", e);
+                        break;
+                    },
+                    None => break,
+                },
+                complete => break,
+            }
+        }
+    }
+}
+
+/// Turns the reading half of the socket into a stream of complete JSON messages.
+///
+/// The socket does not provide any framing, so a single read may contain a partial message
+/// or several messages at once.
+fn as_message_stream<R: AsyncRead + Unpin>(reader: R) -> impl Stream<Item = std::io::Result<Vec<Vec<u8>>>> {
+    futures::stream::unfold(
+        (reader, MessageSplitter::default(), false),
+        |(mut reader, mut splitter, finished)| async move {
+            if finished {
+                return None;
+            }
+            let mut chunk = [0u8; READ_BUFFER_SIZE];
+            match reader.read(&mut chunk).await {
+                Ok(0) => None,
+                Ok(read) => Some((Ok(splitter.push(&chunk[..read])), (reader, splitter, false))),
+                Err(e) => Some((Err(e), (reader, splitter, true))),
+            }
+        },
+    )
+}
+
+/// Splits the data read from the socket into complete top-level JSON objects or arrays.
+///
+/// The scanning state is kept between reads, so every byte is only looked at once no
+/// matter how many reads a large message is split into.
+#[derive(Debug, Default)]
+struct MessageSplitter {
+    /// Unfinished message, starting at its first byte.
+    buffer: Vec<u8>,
+    /// Nesting depth of objects and arrays at the end of the buffer.
+    depth: usize,
+    /// Whether the end of the buffer is inside a string.
+    in_string: bool,
+    /// Whether the end of the buffer is right after a backslash inside a string.
+    escaped: bool,
+}
+
+impl MessageSplitter {
+    /// Appends the data and returns all messages completed by it.
+    fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut messages = Vec::new();
+        let mut invalid = false;
+
+        for &byte in data {
+            if self.depth == 0 {
+                match byte {
+                    b'{' | b'[' => {
+                        self.depth = 1;
+                        self.buffer.push(byte);
+                    }
+                    b' ' | b'\t' | b'\n' | b'\r' => {}
+                    _ => invalid = true,
+                }
+                continue;
+            }
+
+            self.buffer.push(byte);
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                }
+                continue;
+            }
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => self.depth += 1,
+                b'}' | b']' => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        messages.push(std::mem::take(&mut self.buffer));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if invalid {
+            log::error!("Discarding invalid IPC data outside of JSON messages");
+        }
+        messages
+    }
+}
+
+/// IPC transport over a Unix domain socket
+#[derive(Clone)]
+pub struct Ipc {
+    id: Arc<atomic::AtomicUsize>,
+    requests: mpsc::UnboundedSender<TransportMessage>,
+}
+
+impl fmt::Debug for Ipc {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Ipc").field("id", &self.id).finish()
+    }
+}
+
+impl Ipc {
+    /// Create new IPC transport connecting to the socket at given path.
+    pub async fn new<P: AsRef<Path>>(path: P) -> error::Result<Self> {
+        let stream = compat::connect(path.as_ref()).await?;
+        Ok(Self::with_stream(stream))
+    }
+
+    fn with_stream<S>(stream: S) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let id = Arc::new(atomic::AtomicUsize::new(1));
+        let task = IpcServerTask::new(stream);
+        let (sink, stream) = mpsc::unbounded();
+        // Spawn background task for the transport.
+        compat::spawn(task.into_task(stream));
+
+        Self { id, requests: sink }
+    }
+
+    fn send(&self, msg: TransportMessage) -> error::Result {
+        self.requests.unbounded_send(msg).map_err(dropped_err)
+    }
+
+    fn send_request(&self, id: RequestId, request: rpc::Request) -> error::Result<oneshot::Receiver<BatchResult>> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(TransportMessage::Request { id, request, sender })?;
+        Ok(receiver)
+    }
+}
+
+impl Transport for Ipc {
+    type Out = Response<rpc::Value, fn(BatchResult) -> SingleResult>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        let id = self.id.fetch_add(1, atomic::Ordering::AcqRel);
+        let request = helpers::build_request(id, method, params);
+
+        (id, request)
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        let response = self.send_request(id, rpc::Request::Single(request));
+        Response::new(response, batch_to_single)
+    }
+}
+
+impl BatchTransport for Ipc {
+    type Batch = Response<Vec<SingleResult>, fn(BatchResult) -> BatchResult>;
+
+    fn send_batch<T>(&self, requests: T) -> Self::Batch
+    where
+        T: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        let mut it = requests.into_iter();
+        let (id, first) = it.next().map(|x| (x.0, Some(x.1))).unwrap_or_else(|| (0, None));
+        let requests = first.into_iter().chain(it.map(|x| x.1)).collect();
+        let response = self.send_request(id, rpc::Request::Batch(requests));
+        Response::new(response, batch_to_batch)
+    }
+}
+
+impl DuplexTransport for Ipc {
+    type NotificationStream = NotificationStream;
+
+    fn subscribe(&self, id: SubscriptionId) -> error::Result<Self::NotificationStream> {
+        let (sink, stream) = notification_channel(None);
+        self.send(TransportMessage::Subscribe { id, sink })?;
+        Ok(stream)
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> error::Result {
+        self.send(TransportMessage::Unsubscribe { id })
+    }
+}
+
+/// Compatibility layer between async-std and tokio
+#[cfg(feature = "ipc-async-std")]
+mod compat {
+    use std::future::Future;
+    use std::path::Path;
+
+    /// Connect to the Unix domain socket at given path.
+    pub async fn connect(path: &Path) -> std::io::Result<async_std::os::unix::net::UnixStream> {
+        async_std::os::unix::net::UnixStream::connect(path).await
+    }
+
+    /// Spawn the background task.
+    pub fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+        async_std::task::spawn(future);
+    }
+}
+
+/// Compatibility layer between async-std and tokio
+#[cfg(feature = "ipc-tokio")]
+mod compat {
+    use std::future::Future;
+    use std::path::Path;
+    use tokio_util::compat::{Compat, Tokio02AsyncReadCompatExt};
+
+    /// Connect to the Unix domain socket at given path.
+    pub async fn connect(path: &Path) -> std::io::Result<Compat<tokio::net::UnixStream>> {
+        Ok(tokio::net::UnixStream::connect(path).await?.compat())
+    }
+
+    /// Spawn the background task.
+    pub fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+        tokio::spawn(future);
+    }
+}
+
+#[cfg(all(test, feature = "ipc-tokio"))]
+mod tests {
+    use super::*;
+    use crate::api::SubscriptionId;
+    use crate::{rpc, BatchTransport, DuplexTransport, Transport};
+    use futures::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::compat::Tokio02AsyncReadCompatExt;
+
+    #[test]
+    fn should_split_concatenated_messages() {
+        let mut splitter = MessageSplitter::default();
+
+        let messages = splitter.push(
+            br#"{"id":1} [{"id":2},{"id":3}]
+{"id":"#,
+        );
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(&messages[0][..], &br#"{"id":1}"#[..]);
+        assert_eq!(&messages[1][..], &br#"[{"id":2},{"id":3}]"#[..]);
+        assert_eq!(&splitter.buffer[..], &b"{\"id\":"[..]);
+
+        let messages = splitter.push(b"4}");
+        assert_eq!(messages, vec![br#"{"id":4}"#.to_vec()]);
+        assert!(splitter.buffer.is_empty());
+    }
+
+    #[test]
+    fn should_ignore_brackets_in_strings() {
+        let mut splitter = MessageSplitter::default();
+        let message = br#"{"result":"}]\"{[","id":1}"#;
+
+        // feed the message byte by byte to split it at every possible position
+        let messages = message
+            .iter()
+            .flat_map(|byte| splitter.push(std::slice::from_ref(byte)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(messages, vec![message.to_vec()]);
+        assert_eq!(splitter.depth, 0);
+    }
+
+    #[test]
+    fn should_discard_data_outside_of_messages() {
+        let mut splitter = MessageSplitter::default();
+
+        let messages = splitter.push(br#"garbage{"id":1}"#);
+
+        assert_eq!(messages, vec![br#"{"id":1}"#.to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn should_send_a_request() {
+        // given
+        let (client, mut server) = tokio::net::UnixStream::pair().unwrap();
+        let ipc = Ipc::with_stream(client.compat());
+
+        // when
+        let res = ipc.execute("eth_accounts", vec![rpc::Value::String("1".into())]);
+
+        // then
+        let mut buf = [0u8; 1024];
+        let read = server.read(&mut buf).await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..read]),
+            Ok(r#"{"jsonrpc":"2.0","method":"eth_accounts","params":["1"],"id":1}"#)
+        );
+        // respond in two separate writes
+        server.write_all(br#"{"jsonrpc":"2.0","#).await.unwrap();
+        server.write_all(br#""id":1,"result":"x"}"#).await.unwrap();
+
+        assert_eq!(res.await, Ok(rpc::Value::String("x".into())));
+    }
+
+    #[tokio::test]
+    async fn should_send_a_batch_request() {
+        // given
+        let (client, mut server) = tokio::net::UnixStream::pair().unwrap();
+        let ipc = Ipc::with_stream(client.compat());
+        let requests = vec![
+            ipc.prepare("eth_accounts", vec![]),
+            ipc.prepare("eth_blockNumber", vec![]),
+        ];
+
+        // when
+        let res = ipc.send_batch(requests);
+
+        // then
+        let mut buf = [0u8; 1024];
+        let read = server.read(&mut buf).await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..read]),
+            Ok(concat!(
+                r#"[{"jsonrpc":"2.0","method":"eth_accounts","params":[],"id":1},"#,
+                r#"{"jsonrpc":"2.0","method":"eth_blockNumber","params":[],"id":2}]"#
+            ))
+        );
+        server
+            .write_all(br#"[{"jsonrpc":"2.0","id":1,"result":[]},{"jsonrpc":"2.0","id":2,"result":"0x1"}]"#)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res.await,
+            Ok(vec![
+                Ok(rpc::Value::Array(vec![])),
+                Ok(rpc::Value::String("0x1".into()))
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn should_receive_notifications() {
+        // given
+        let (client, mut server) = tokio::net::UnixStream::pair().unwrap();
+        let ipc = Ipc::with_stream(client.compat());
+        let id = SubscriptionId::from("0x1".to_owned());
+        let mut stream = ipc.subscribe(id.clone()).unwrap();
+        // make sure the subscription is registered before sending notifications
+        let res = ipc.execute("eth_accounts", vec![]);
+        let mut buf = [0u8; 1024];
+        server.read(&mut buf).await.unwrap();
+        server
+            .write_all(br#"{"jsonrpc":"2.0","id":1,"result":[]}"#)
+            .await
+            .unwrap();
+        assert_eq!(res.await, Ok(rpc::Value::Array(vec![])));
+
+        // when
+        server
+            .write_all(
+                concat!(
+                    r#"{"jsonrpc":"2.0","method":"eth_subscription","params":{"subscription":"0x1","result":1}}"#,
+                    r#"{"jsonrpc":"2.0","method":"eth_subscription","params":{"subscription":"0x1","result":2}}"#,
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(stream.next().await, Some(rpc::Value::from(1)));
+        assert_eq!(stream.next().await, Some(rpc::Value::from(2)));
+        ipc.unsubscribe(id).unwrap();
+    }
+}
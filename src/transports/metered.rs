@@ -0,0 +1,357 @@
+//! Transport reporting request metrics
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::api::SubscriptionId;
+use crate::error::{self, Error};
+use crate::rpc;
+use crate::{BatchTransport, DuplexTransport, RequestId, Transport};
+use futures::task::{Context, Poll};
+use futures::{Future, FutureExt};
+
+/// Class of an error returned by a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// Server could not be reached.
+    Unreachable,
+    /// Response could not be decoded.
+    Decoder,
+    /// Response was invalid.
+    InvalidResponse,
+    /// Transport failed.
+    Transport,
+    /// Node returned an RPC error with given code.
+    Rpc(i64),
+    /// I/O failed.
+    Io,
+    /// Any other error.
+    Other,
+}
+
+impl From<&Error> for ErrorClass {
+    fn from(err: &Error) -> Self {
+        match err {
+            Error::Unreachable => ErrorClass::Unreachable,
+            Error::Decoder(_) => ErrorClass::Decoder,
+            Error::InvalidResponse(_) => ErrorClass::InvalidResponse,
+            Error::Transport(_) => ErrorClass::Transport,
+            Error::Rpc(err) => ErrorClass::Rpc(err.code.code()),
+            Error::Io(_) => ErrorClass::Io,
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+/// Measurements of a single JSON-RPC call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestMetrics {
+    /// ID of the request.
+    pub id: RequestId,
+    /// Called method.
+    pub method: String,
+    /// Time from sending the request until receiving the response (of the whole batch for batched calls).
+    pub latency: Duration,
+    /// Size of the JSON-serialized call in bytes.
+    pub request_size: usize,
+    /// Size of the JSON-serialized result or error in bytes.
+    pub response_size: usize,
+    /// Class of the error if the call failed.
+    pub error: Option<ErrorClass>,
+    /// Number of calls in the batch if the call was sent in a batch.
+    pub batch_size: Option<usize>,
+}
+
+/// Receives the metrics of completed calls.
+///
+/// Implemented for closures taking `&RequestMetrics`.
+pub trait Observer: Send + Sync {
+    /// Called for every call after its response arrived.
+    fn observe(&self, metrics: &RequestMetrics);
+}
+
+impl<F: Fn(&RequestMetrics) + Send + Sync> Observer for F {
+    fn observe(&self, metrics: &RequestMetrics) {
+        self(metrics)
+    }
+}
+
+/// Transport passing metrics of every call to an `Observer`.
+///
+/// With `tracing` feature every request is also instrumented with a span carrying its method and ID.
+#[derive(Clone)]
+pub struct Metered<T> {
+    transport: T,
+    observer: Arc<dyn Observer>,
+}
+
+impl<T: fmt::Debug> fmt::Debug for Metered<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Metered").field("transport", &self.transport).finish()
+    }
+}
+
+impl<T: Transport> Metered<T> {
+    /// Wraps given transport, reporting metrics to `observer`.
+    pub fn new<O: Observer + 'static>(transport: T, observer: O) -> Self {
+        Metered {
+            transport,
+            observer: Arc::new(observer),
+        }
+    }
+
+    /// Returns a reference to the inner transport.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    fn measure<F>(&self, future: F, requests: &[(RequestId, rpc::Call)], batch: bool) -> MeteredFuture<F> {
+        let calls: Vec<_> = requests
+            .iter()
+            .map(|(id, call)| {
+                let method = match call {
+                    rpc::Call::MethodCall(call) => call.method.clone(),
+                    rpc::Call::Notification(call) => call.method.clone(),
+                    rpc::Call::Invalid { .. } => String::new(),
+                };
+                let size = serde_json::to_vec(call).map(|json| json.len()).unwrap_or_default();
+                (*id, method, size)
+            })
+            .collect();
+        MeteredFuture {
+            #[cfg(feature = "tracing")]
+            span: match calls.as_slice() {
+                [(id, method, _)] if !batch => tracing::debug_span!("rpc", method = %method, id = *id),
+                _ => tracing::debug_span!("rpc_batch", size = calls.len()),
+            },
+            future,
+            batch_size: if batch { Some(calls.len()) } else { None },
+            calls,
+            observer: self.observer.clone(),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl<T: Transport> Transport for Metered<T> {
+    type Out = MeteredFuture<T::Out>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        self.transport.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        let future = self.transport.send(id, request.clone());
+        self.measure(future, &[(id, request)], false)
+    }
+}
+
+impl<T: BatchTransport> BatchTransport for Metered<T> {
+    type Batch = MeteredFuture<Pin<Box<T::Batch>>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        let requests: Vec<_> = requests.into_iter().collect();
+        let batch = Box::pin(self.transport.send_batch(requests.clone()));
+        self.measure(batch, &requests, true)
+    }
+}
+
+impl<T: DuplexTransport> DuplexTransport for Metered<T> {
+    type NotificationStream = T::NotificationStream;
+
+    fn subscribe(&self, id: SubscriptionId) -> error::Result<Self::NotificationStream> {
+        self.transport.subscribe(id)
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> error::Result {
+        self.transport.unsubscribe(id)
+    }
+}
+
+/// Responses which can be measured.
+pub trait Measurable {
+    /// Returns the results of individual calls, or the error of the whole request.
+    fn results(&self) -> Result<Vec<&error::Result<rpc::Value>>, &Error>;
+}
+
+impl Measurable for error::Result<rpc::Value> {
+    fn results(&self) -> Result<Vec<&error::Result<rpc::Value>>, &Error> {
+        Ok(vec![self])
+    }
+}
+
+impl Measurable for error::Result<Vec<error::Result<rpc::Value>>> {
+    fn results(&self) -> Result<Vec<&error::Result<rpc::Value>>, &Error> {
+        match self {
+            Ok(results) => Ok(results.iter().collect()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// A request being measured.
+pub struct MeteredFuture<F> {
+    future: F,
+    calls: Vec<(RequestId, String, usize)>,
+    batch_size: Option<usize>,
+    observer: Arc<dyn Observer>,
+    started: Instant,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl<F> MeteredFuture<F> {
+    fn report(&self, idx: usize, latency: Duration, result: Result<&rpc::Value, &Error>) {
+        let (id, ref method, request_size) = self.calls[idx];
+        let response_size = match result {
+            Ok(value) => serde_json::to_vec(value).map(|json| json.len()).unwrap_or_default(),
+            Err(Error::Rpc(err)) => serde_json::to_vec(err).map(|json| json.len()).unwrap_or_default(),
+            Err(_) => 0,
+        };
+        let error = result.err().map(ErrorClass::from);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(method = %method, id, latency_us = latency.as_micros() as u64, error = ?error, "response");
+
+        self.observer.observe(&RequestMetrics {
+            id,
+            method: method.clone(),
+            latency,
+            request_size,
+            response_size,
+            error,
+            batch_size: self.batch_size,
+        });
+    }
+}
+
+impl<F> Future for MeteredFuture<F>
+where
+    F: Future + Unpin,
+    F::Output: Measurable,
+{
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        #[cfg(feature = "tracing")]
+        let span = self.span.clone();
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        let output = ready!(self.future.poll_unpin(cx));
+        let latency = self.started.elapsed();
+        match output.results() {
+            Ok(results) => {
+                for (idx, result) in results.into_iter().enumerate().take(self.calls.len()) {
+                    self.report(idx, latency, result.as_ref());
+                }
+            }
+            Err(err) => {
+                for idx in 0..self.calls.len() {
+                    self.report(idx, latency, Err(err));
+                }
+            }
+        }
+        Poll::Ready(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorClass, Metered, RequestMetrics};
+    use crate::error::{self, Error};
+    use crate::rpc;
+    use crate::{BatchTransport, RequestId, Transport};
+    use futures::executor::block_on;
+    use futures::future;
+    use parking_lot::Mutex;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone)]
+    struct Node;
+
+    impl Node {
+        fn respond(request: rpc::Call) -> error::Result<rpc::Value> {
+            match request {
+                rpc::Call::MethodCall(call) if call.method == "eth_call" => Err(Error::Rpc(rpc::Error {
+                    code: rpc::ErrorCode::ServerError(3),
+                    message: "execution reverted".into(),
+                    data: None,
+                })),
+                _ => Ok(json!("0x2a")),
+            }
+        }
+    }
+
+    impl Transport for Node {
+        type Out = future::Ready<error::Result<rpc::Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+            (7, crate::helpers::build_request(7, method, params))
+        }
+
+        fn send(&self, _id: RequestId, request: rpc::Call) -> Self::Out {
+            future::ready(Self::respond(request))
+        }
+    }
+
+    impl BatchTransport for Node {
+        type Batch = future::Ready<error::Result<Vec<error::Result<rpc::Value>>>>;
+
+        fn send_batch<I>(&self, requests: I) -> Self::Batch
+        where
+            I: IntoIterator<Item = (RequestId, rpc::Call)>,
+        {
+            future::ready(Ok(requests.into_iter().map(|(_, call)| Self::respond(call)).collect()))
+        }
+    }
+
+    fn metered() -> (Metered<Node>, Arc<Mutex<Vec<RequestMetrics>>>) {
+        let metrics = Arc::new(Mutex::new(Vec::new()));
+        let observed = metrics.clone();
+        let transport = Metered::new(Node, move |metrics: &RequestMetrics| {
+            observed.lock().push(metrics.clone())
+        });
+        (transport, metrics)
+    }
+
+    #[test]
+    fn should_report_single_calls() {
+        let (transport, metrics) = metered();
+
+        block_on(transport.execute("eth_blockNumber", vec![])).unwrap();
+        block_on(transport.execute("eth_call", vec![json!({})])).unwrap_err();
+
+        let metrics = metrics.lock();
+        assert_eq!(metrics.len(), 2);
+        assert_eq!((metrics[0].id, metrics[0].method.as_str()), (7, "eth_blockNumber"));
+        assert_eq!(metrics[0].request_size, 63);
+        assert_eq!(metrics[0].response_size, 6);
+        assert_eq!(metrics[0].error, None);
+        assert_eq!(metrics[0].batch_size, None);
+        assert_eq!(metrics[1].error, Some(ErrorClass::Rpc(3)));
+    }
+
+    #[test]
+    fn should_report_every_call_of_batch() {
+        let (transport, metrics) = metered();
+
+        let requests = vec![
+            transport.prepare("eth_blockNumber", vec![]),
+            transport.prepare("eth_call", vec![]),
+        ];
+        block_on(transport.send_batch(requests)).unwrap();
+
+        let metrics = metrics.lock();
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics[0].method, "eth_blockNumber");
+        assert_eq!(metrics[1].method, "eth_call");
+        assert_eq!(metrics[1].error, Some(ErrorClass::Rpc(3)));
+        assert!(metrics.iter().all(|metrics| metrics.batch_size == Some(2)));
+    }
+}
@@ -0,0 +1,266 @@
+//! Mock Transport for testing code using web3
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::Arc;
+
+use crate::api::SubscriptionId;
+use crate::error::{self, Error};
+use crate::helpers;
+use crate::rpc;
+use crate::{BatchTransport, DuplexTransport, RequestId, Transport};
+use futures::channel::mpsc;
+use futures::future::{self, Ready};
+use parking_lot::Mutex;
+
+type Handler = Arc<dyn Fn(&[rpc::Value]) -> error::Result<rpc::Value> + Send + Sync>;
+
+#[derive(Default)]
+struct MockState {
+    handlers: HashMap<String, Handler>,
+    calls: Vec<(String, Vec<rpc::Value>)>,
+    asserted: usize,
+    subscriptions: BTreeMap<SubscriptionId, mpsc::UnboundedSender<rpc::Value>>,
+}
+
+/// Transport answering requests with handlers registered per method.
+///
+/// All clones share the handlers and the log of calls, so a clone can be passed to the code under test
+/// and the original used to make assertions. Calls to methods without a handler fail with
+/// "Method not found" RPC error.
+///
+/// Available with `test-utils` feature.
+#[derive(Clone)]
+pub struct Mock {
+    id: Arc<AtomicUsize>,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl Default for Mock {
+    fn default() -> Self {
+        Mock {
+            id: Arc::new(AtomicUsize::new(1)),
+            state: Default::default(),
+        }
+    }
+}
+
+impl fmt::Debug for Mock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock();
+        f.debug_struct("Mock")
+            .field("handlers", &state.handlers.keys().collect::<Vec<_>>())
+            .field("calls", &state.calls)
+            .field("subscriptions", &state.subscriptions.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Mock {
+    /// Creates new transport without any handlers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers every call to `method` with `value`.
+    pub fn on<V: Into<rpc::Value>>(&self, method: &str, value: V) -> &Self {
+        let value = value.into();
+        self.on_call(method, move |_| Ok(value.clone()))
+    }
+
+    /// Fails every call to `method` with `error`.
+    pub fn fail(&self, method: &str, error: Error) -> &Self {
+        self.on_call(method, move |_| Err(error.clone()))
+    }
+
+    /// Answers calls to `method` with the result of `handler` invoked with the call parameters.
+    ///
+    /// Replaces the handler registered for the method before.
+    pub fn on_call<F>(&self, method: &str, handler: F) -> &Self
+    where
+        F: Fn(&[rpc::Value]) -> error::Result<rpc::Value> + Send + Sync + 'static,
+    {
+        self.state.lock().handlers.insert(method.into(), Arc::new(handler));
+        self
+    }
+
+    /// Returns all calls made so far, in order.
+    pub fn calls(&self) -> Vec<(String, Vec<rpc::Value>)> {
+        self.state.lock().calls.clone()
+    }
+
+    /// Asserts that the next call not asserted yet was made to `method` with `params`.
+    pub fn assert_call(&self, method: &str, params: &[rpc::Value]) {
+        let mut state = self.state.lock();
+        let idx = state.asserted;
+        state.asserted += 1;
+
+        let (m, p) = state
+            .calls
+            .get(idx)
+            .unwrap_or_else(|| panic!("Expected call to {}, got no more calls.", method));
+        assert_eq!(m, method);
+        assert_eq!(p.as_slice(), params);
+    }
+
+    /// Asserts that no calls were made apart from the ones already asserted.
+    pub fn assert_no_more_calls(&self) {
+        let state = self.state.lock();
+        assert_eq!(
+            state.asserted,
+            state.calls.len(),
+            "Expected no more calls, got: {:?}",
+            &state.calls[state.asserted..]
+        );
+    }
+
+    /// Pushes a notification to the stream of given subscription.
+    ///
+    /// # Panics
+    ///
+    /// If there is no such subscription.
+    pub fn notify<V: Into<rpc::Value>>(&self, id: &SubscriptionId, value: V) {
+        let state = self.state.lock();
+        let sink = state
+            .subscriptions
+            .get(id)
+            .unwrap_or_else(|| panic!("No subscription with id {:?}.", id));
+        // The stream might have been dropped without unsubscribing.
+        let _ = sink.unbounded_send(value.into());
+    }
+
+    fn respond(&self, request: rpc::Call) -> error::Result<rpc::Value> {
+        let (method, params) = match request {
+            rpc::Call::MethodCall(call) => (call.method, call.params),
+            rpc::Call::Notification(call) => (call.method, call.params),
+            rpc::Call::Invalid { .. } => return Err(Error::Rpc(rpc::Error::invalid_request())),
+        };
+        let params = match params {
+            rpc::Params::Array(params) => params,
+            rpc::Params::Map(params) => vec![params.into()],
+            rpc::Params::None => vec![],
+        };
+        let handler = {
+            let mut state = self.state.lock();
+            state.calls.push((method.clone(), params.clone()));
+            state.handlers.get(&method).cloned()
+        };
+        match handler {
+            Some(handler) => handler(&params),
+            None => Err(Error::Rpc(rpc::Error::method_not_found())),
+        }
+    }
+}
+
+impl Transport for Mock {
+    type Out = Ready<error::Result<rpc::Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        let id = self.id.fetch_add(1, atomic::Ordering::AcqRel);
+        let request = helpers::build_request(id, method, params);
+
+        (id, request)
+    }
+
+    fn send(&self, _id: RequestId, request: rpc::Call) -> Self::Out {
+        future::ready(self.respond(request))
+    }
+}
+
+impl BatchTransport for Mock {
+    type Batch = Ready<error::Result<Vec<error::Result<rpc::Value>>>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        future::ready(Ok(requests
+            .into_iter()
+            .map(|(_, request)| self.respond(request))
+            .collect()))
+    }
+}
+
+impl DuplexTransport for Mock {
+    type NotificationStream = mpsc::UnboundedReceiver<rpc::Value>;
+
+    fn subscribe(&self, id: SubscriptionId) -> error::Result<Self::NotificationStream> {
+        let (sink, stream) = mpsc::unbounded();
+        self.state.lock().subscriptions.insert(id, sink);
+        Ok(stream)
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> error::Result {
+        self.state.lock().subscriptions.remove(&id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mock;
+    use crate::api::{SubscriptionId, Web3};
+    use crate::error::Error;
+    use crate::rpc;
+    use crate::types::U64;
+    use crate::{BatchTransport, DuplexTransport, Transport};
+    use futures::executor::block_on;
+    use futures::StreamExt;
+    use serde_json::json;
+
+    #[test]
+    fn should_answer_with_registered_handlers() {
+        let mock = Mock::new();
+        mock.on("eth_blockNumber", "0x10")
+            .on_call("eth_getBalance", |params| Ok(params[1].clone()))
+            .fail("eth_sendTransaction", Error::Unreachable);
+        let web3 = Web3::new(mock.clone());
+
+        assert_eq!(block_on(web3.eth().block_number()), Ok(U64::from(16)));
+        assert_eq!(
+            block_on(mock.execute("eth_getBalance", vec![json!("0x00"), json!("0x5")])),
+            Ok(json!("0x5"))
+        );
+        assert_eq!(
+            block_on(mock.execute("eth_sendTransaction", vec![])),
+            Err(Error::Unreachable)
+        );
+        assert_eq!(
+            block_on(mock.execute("eth_chainId", vec![])),
+            Err(Error::Rpc(rpc::Error::method_not_found()))
+        );
+
+        mock.assert_call("eth_blockNumber", &[]);
+        mock.assert_call("eth_getBalance", &[json!("0x00"), json!("0x5")]);
+        mock.assert_call("eth_sendTransaction", &[]);
+        mock.assert_call("eth_chainId", &[]);
+        mock.assert_no_more_calls();
+    }
+
+    #[test]
+    fn should_answer_batches() {
+        let mock = Mock::new();
+        mock.on("eth_chainId", "0x1");
+
+        let requests = vec![mock.prepare("eth_chainId", vec![]), mock.prepare("net_version", vec![])];
+        let results = block_on(mock.send_batch(requests)).unwrap();
+
+        assert_eq!(results[0], Ok(json!("0x1")));
+        assert!(results[1].is_err());
+        assert_eq!(mock.calls().len(), 2);
+    }
+
+    #[test]
+    fn should_push_notifications_to_subscribers() {
+        let mock = Mock::new();
+        let id = SubscriptionId::from("0x1".to_owned());
+        let mut stream = mock.subscribe(id.clone()).unwrap();
+
+        mock.notify(&id, json!({ "number": "0x1" }));
+        mock.unsubscribe(id).unwrap();
+
+        assert_eq!(block_on(stream.next()), Some(json!({ "number": "0x1" })));
+        assert_eq!(block_on(stream.next()), None);
+    }
+}
@@ -1,9 +1,25 @@
 //! Supported Ethereum JSON-RPC transports.
 
+pub mod balanced;
+pub use self::balanced::Balanced;
 pub mod batch;
-pub use self::batch::Batch;
+pub use self::batch::{AutoBatch, Batch};
+pub mod cached;
+pub use self::cached::Cached;
 pub mod either;
 pub use self::either::Either;
+pub mod in_process;
+pub use self::in_process::InProcess;
+pub mod metered;
+pub use self::metered::Metered;
+pub mod quorum;
+pub use self::quorum::Quorum;
+pub mod rate_limited;
+pub use self::rate_limited::RateLimited;
+pub mod recorder;
+pub use self::recorder::{Recorder, Replay};
+pub mod retry;
+pub use self::retry::Retry;
 
 #[cfg(feature = "http")]
 pub mod http;
@@ -15,6 +31,33 @@ pub mod ws;
 #[cfg(any(feature = "ws-tokio", feature = "ws-async-std"))]
 pub use self::ws::WebSocket;
 
+#[cfg(any(test, feature = "test-utils"))]
+pub mod mock;
+#[cfg(any(test, feature = "test-utils"))]
+pub use self::mock::Mock;
+
+#[cfg(any(feature = "http", feature = "ws-tokio", feature = "ws-async-std"))]
+pub mod proxy;
+#[cfg(any(feature = "http", feature = "ws-tokio", feature = "ws-async-std"))]
+pub use self::proxy::Proxy;
+
+#[cfg(feature = "native-tls")]
+pub mod tls;
+#[cfg(feature = "native-tls")]
+pub use self::tls::TlsConfig;
+
+#[cfg(all(unix, any(feature = "ipc-tokio", feature = "ipc-async-std")))]
+pub mod ipc;
+#[cfg(all(unix, any(feature = "ipc-tokio", feature = "ipc-async-std")))]
+pub use self::ipc::Ipc;
+
+#[cfg(any(
+    feature = "ws-tokio",
+    feature = "ws-async-std",
+    all(unix, any(feature = "ipc-tokio", feature = "ipc-async-std"))
+))]
+mod shared;
+
 #[cfg(feature = "url")]
 impl From<url::ParseError> for crate::Error {
     fn from(err: url::ParseError) -> Self {
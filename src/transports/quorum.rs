@@ -0,0 +1,236 @@
+//! Quorum Transport
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::Arc;
+
+use crate::error::{self, Error};
+use crate::helpers;
+use crate::rpc;
+use crate::{RequestId, Transport};
+use futures::task::{Context, Poll};
+use futures::{Future, FutureExt};
+
+/// Responses of the nodes which didn't reach the quorum.
+#[derive(Debug, Clone)]
+pub struct QuorumError {
+    /// Number of identical responses required.
+    pub threshold: usize,
+    /// Responses of every inner transport, in order.
+    pub responses: Vec<error::Result<rpc::Value>>,
+}
+
+impl fmt::Display for QuorumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} identical responses required, got:", self.threshold)?;
+        for (idx, response) in self.responses.iter().enumerate() {
+            match response {
+                Ok(value) => write!(f, " [{}] {}", idx, value)?,
+                Err(err) => write!(f, " [{}] {}", idx, err)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for QuorumError {}
+
+/// Transport sending every request to several nodes and accepting the response once enough of them agree.
+///
+/// Fails with `Error::Quorum` when the threshold can't be reached.
+#[derive(Debug, Clone)]
+pub struct Quorum<T> {
+    id: Arc<AtomicUsize>,
+    transports: Arc<Vec<T>>,
+    threshold: usize,
+}
+
+impl<T: Transport> Quorum<T> {
+    /// Creates new transport requiring `threshold` identical responses.
+    ///
+    /// # Panics
+    ///
+    /// If `threshold` is zero or greater than the number of transports.
+    pub fn new(transports: Vec<T>, threshold: usize) -> Self {
+        assert!(
+            threshold > 0 && threshold <= transports.len(),
+            "Quorum threshold must be between 1 and the number of transports."
+        );
+        Quorum {
+            id: Arc::new(AtomicUsize::new(1)),
+            transports: Arc::new(transports),
+            threshold,
+        }
+    }
+}
+
+impl<T: Transport> Transport for Quorum<T> {
+    type Out = QuorumFuture<T::Out>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        let id = self.id.fetch_add(1, atomic::Ordering::AcqRel);
+        let request = helpers::build_request(id, method, params);
+
+        (id, request)
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        QuorumFuture {
+            threshold: self.threshold,
+            pending: self
+                .transports
+                .iter()
+                .map(|transport| Some(transport.send(id, request.clone())))
+                .collect(),
+            responses: self.transports.iter().map(|_| None).collect(),
+        }
+    }
+}
+
+/// A request waiting for the quorum.
+pub struct QuorumFuture<F> {
+    threshold: usize,
+    pending: Vec<Option<F>>,
+    responses: Vec<Option<error::Result<rpc::Value>>>,
+}
+
+impl<F> QuorumFuture<F> {
+    /// Returns the value with most votes and the number of them.
+    fn leader(&self) -> Option<(&rpc::Value, usize)> {
+        let values = self.responses.iter().filter_map(|response| match response {
+            Some(Ok(value)) => Some(value),
+            _ => None,
+        });
+        values
+            .clone()
+            .map(|value| (value, values.clone().filter(|other| *other == value).count()))
+            .max_by_key(|(_, votes)| *votes)
+    }
+}
+
+impl<F> Future for QuorumFuture<F>
+where
+    F: Future<Output = error::Result<rpc::Value>> + Unpin,
+{
+    type Output = error::Result<rpc::Value>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        for (pending, response) in this.pending.iter_mut().zip(this.responses.iter_mut()) {
+            if let Some(future) = pending {
+                if let Poll::Ready(result) = future.poll_unpin(cx) {
+                    *pending = None;
+                    *response = Some(result);
+                }
+            }
+        }
+
+        let waiting = this.pending.iter().filter(|pending| pending.is_some()).count();
+        let votes = match this.leader() {
+            Some((value, votes)) if votes >= this.threshold => return Poll::Ready(Ok(value.clone())),
+            Some((_, votes)) => votes,
+            None => 0,
+        };
+        if votes + waiting >= this.threshold {
+            return Poll::Pending;
+        }
+
+        let responses = this
+            .responses
+            .iter_mut()
+            .map(|response| {
+                response
+                    .take()
+                    .unwrap_or_else(|| Err(Error::Transport("No response.".into())))
+            })
+            .collect();
+        Poll::Ready(Err(Error::Quorum(QuorumError {
+            threshold: this.threshold,
+            responses,
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Quorum, QuorumError};
+    use crate::error::{self, Error};
+    use crate::rpc;
+    use crate::{RequestId, Transport};
+    use futures::channel::oneshot;
+    use futures::executor::block_on;
+    use futures::task::Context;
+    use futures::{future, FutureExt};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Debug, Clone)]
+    struct Node(error::Result<rpc::Value>);
+
+    impl Transport for Node {
+        type Out = future::Ready<error::Result<rpc::Value>>;
+
+        fn prepare(&self, _method: &str, _params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+            unreachable!("Requests are prepared by the quorum transport.")
+        }
+
+        fn send(&self, _id: RequestId, _request: rpc::Call) -> Self::Out {
+            future::ready(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn should_return_value_agreed_by_threshold() {
+        let transport = Quorum::new(vec![Node(Ok(1.into())), Node(Ok(2.into())), Node(Ok(1.into()))], 2);
+
+        assert_eq!(block_on(transport.execute("eth_call", vec![])), Ok(1.into()));
+    }
+
+    #[test]
+    fn should_list_divergent_responses() {
+        let transport = Quorum::new(
+            vec![Node(Ok(1.into())), Node(Ok(2.into())), Node(Err(Error::Unreachable))],
+            2,
+        );
+
+        assert_eq!(
+            block_on(transport.execute("eth_call", vec![])),
+            Err(Error::Quorum(QuorumError {
+                threshold: 2,
+                responses: vec![Ok(1.into()), Ok(2.into()), Err(Error::Unreachable)],
+            }))
+        );
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct SlowNode(Rc<RefCell<Vec<oneshot::Sender<error::Result<rpc::Value>>>>>);
+
+    impl Transport for SlowNode {
+        type Out = future::BoxFuture<'static, error::Result<rpc::Value>>;
+
+        fn prepare(&self, _method: &str, _params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+            unreachable!("Requests are prepared by the quorum transport.")
+        }
+
+        fn send(&self, _id: RequestId, _request: rpc::Call) -> Self::Out {
+            let (tx, rx) = oneshot::channel();
+            self.0.borrow_mut().push(tx);
+            rx.map(|result| result.unwrap_or(Err(Error::Internal))).boxed()
+        }
+    }
+
+    #[test]
+    fn should_not_wait_for_remaining_nodes() {
+        let nodes = vec![SlowNode::default(), SlowNode::default(), SlowNode::default()];
+        let transport = Quorum::new(nodes.clone(), 2);
+        let mut response = transport.execute("eth_getBalance", vec![]);
+
+        let reply = |idx: usize| nodes[idx].0.borrow_mut().pop().unwrap().send(Ok(5.into())).unwrap();
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        reply(0);
+        assert!(response.poll_unpin(&mut cx).is_pending());
+        reply(2);
+        assert_eq!(block_on(response), Ok(5.into()));
+    }
+}
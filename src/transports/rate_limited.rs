@@ -0,0 +1,338 @@
+//! Rate-limiting Transport
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error;
+use crate::rpc;
+use crate::{BatchTransport, RequestId, Transport};
+use futures::task::{Context, Poll, Waker};
+use futures::{Future, FutureExt};
+use futures_timer::Delay;
+use parking_lot::Mutex;
+
+/// Limits of the `RateLimited` transport.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimits {
+    /// Maximal weight of requests sent per second (`None` for no limit).
+    ///
+    /// Up to this weight may be sent at once after the transport was idle.
+    pub requests_per_second: Option<u32>,
+    /// Maximal number of requests awaiting response (`None` for no limit).
+    pub max_in_flight: Option<usize>,
+    /// Weights of methods more expensive than others (e.g. `eth_getLogs`); other methods weigh 1.
+    pub method_weights: HashMap<String, u32>,
+}
+
+impl RateLimits {
+    fn weight(&self, call: &rpc::Call) -> u32 {
+        let method = match call {
+            rpc::Call::MethodCall(call) => &call.method,
+            rpc::Call::Notification(call) => &call.method,
+            rpc::Call::Invalid { .. } => return 1,
+        };
+        self.method_weights.get(method).copied().unwrap_or(1)
+    }
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    tokens: f64,
+    updated: Instant,
+    in_flight: usize,
+    waiting: Vec<Waker>,
+}
+
+#[derive(Debug)]
+struct Limiter {
+    limits: RateLimits,
+    state: Mutex<LimiterState>,
+}
+
+impl Limiter {
+    /// Reserves capacity for a request of given weight at the instant `now`.
+    ///
+    /// Returns the delay after which the rate allows sending if the request has to wait for it.
+    /// Requests waiting for a free in-flight slot are woken up when one is released.
+    fn try_acquire(&self, weight: u32, waker: &Waker, now: Instant) -> Result<(), Option<Duration>> {
+        let mut state = self.state.lock();
+        if matches!(self.limits.max_in_flight, Some(max) if state.in_flight >= max) {
+            state.waiting.push(waker.clone());
+            return Err(None);
+        }
+        if let Some(rate) = self.limits.requests_per_second {
+            let rate = f64::from(rate);
+            let elapsed = now.duration_since(state.updated).as_secs_f64();
+            state.tokens = (state.tokens + elapsed * rate).min(rate);
+            state.updated = now;
+            // Requests heavier than the whole bucket are sent once it is full and leave it in debt.
+            let required = f64::from(weight).min(rate);
+            if state.tokens < required {
+                return Err(Some(Duration::from_secs_f64((required - state.tokens) / rate)));
+            }
+            state.tokens -= f64::from(weight);
+        }
+        state.in_flight += 1;
+        Ok(())
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock();
+        state.in_flight -= 1;
+        for waker in state.waiting.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Transport limiting the rate and concurrency of requests.
+///
+/// The limits are shared by all clones of the transport.
+#[derive(Debug, Clone)]
+pub struct RateLimited<T> {
+    transport: T,
+    limiter: Arc<Limiter>,
+}
+
+impl<T: Transport> RateLimited<T> {
+    /// Wraps given transport, enforcing given limits.
+    pub fn new(transport: T, limits: RateLimits) -> Self {
+        let tokens = limits.requests_per_second.map(f64::from).unwrap_or_default();
+        RateLimited {
+            transport,
+            limiter: Arc::new(Limiter {
+                limits,
+                state: Mutex::new(LimiterState {
+                    tokens,
+                    updated: Instant::now(),
+                    in_flight: 0,
+                    waiting: Vec::new(),
+                }),
+            }),
+        }
+    }
+
+    /// Returns a reference to the inner transport.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    fn request<R, F>(&self, request: R, weight: u32, send: fn(&T, R) -> F) -> RateLimitedFuture<T, R, F> {
+        RateLimitedFuture {
+            transport: self.transport.clone(),
+            limiter: self.limiter.clone(),
+            weight,
+            send,
+            request: Some(request),
+            delay: None,
+            sending: None,
+        }
+    }
+}
+
+impl<T: Transport> Transport for RateLimited<T> {
+    type Out = RateLimitedFuture<T, (RequestId, rpc::Call), T::Out>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        self.transport.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        let weight = self.limiter.limits.weight(&request);
+        self.request((id, request), weight, |transport, (id, request)| {
+            transport.send(id, request)
+        })
+    }
+}
+
+impl<T: BatchTransport> BatchTransport for RateLimited<T> {
+    type Batch = RateLimitedFuture<T, Vec<(RequestId, rpc::Call)>, T::Batch>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        let requests: Vec<_> = requests.into_iter().collect();
+        let weight = requests.iter().map(|(_, call)| self.limiter.limits.weight(call)).sum();
+        self.request(requests, weight, |transport, requests| transport.send_batch(requests))
+    }
+}
+
+/// A request waiting for the limits of `RateLimited` transport.
+pub struct RateLimitedFuture<T, R, F> {
+    transport: T,
+    limiter: Arc<Limiter>,
+    weight: u32,
+    send: fn(&T, R) -> F,
+    request: Option<R>,
+    delay: Option<Delay>,
+    sending: Option<Pin<Box<F>>>,
+}
+
+impl<T, R, F, O> Future for RateLimitedFuture<T, R, F>
+where
+    T: Unpin,
+    R: Unpin,
+    F: Future<Output = error::Result<O>>,
+{
+    type Output = error::Result<O>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            if let Some(ref mut future) = this.sending {
+                let result = ready!(future.as_mut().poll(cx));
+                this.sending = None;
+                this.limiter.release();
+                return Poll::Ready(result);
+            }
+            if let Some(ref mut delay) = this.delay {
+                ready!(delay.poll_unpin(cx));
+                this.delay = None;
+            }
+            match this.limiter.try_acquire(this.weight, cx.waker(), Instant::now()) {
+                Ok(()) => {
+                    let request = this.request.take().expect("Poll after Ready.");
+                    this.sending = Some(Box::pin((this.send)(&this.transport, request)));
+                }
+                Err(Some(wait)) => this.delay = Some(Delay::new(wait)),
+                Err(None) => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T, R, F> Drop for RateLimitedFuture<T, R, F> {
+    fn drop(&mut self) {
+        if self.sending.is_some() {
+            self.limiter.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimited, RateLimits};
+    use crate::error::{self, Error};
+    use crate::rpc;
+    use crate::{RequestId, Transport};
+    use futures::channel::oneshot;
+    use futures::executor::block_on;
+    use futures::task::Context;
+    use futures::{future, FutureExt};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    #[derive(Debug, Clone, Default)]
+    struct Node(Rc<RefCell<Vec<oneshot::Sender<error::Result<rpc::Value>>>>>);
+
+    impl Transport for Node {
+        type Out = future::BoxFuture<'static, error::Result<rpc::Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+            (1, crate::helpers::build_request(1, method, params))
+        }
+
+        fn send(&self, _id: RequestId, _request: rpc::Call) -> Self::Out {
+            let (tx, rx) = oneshot::channel();
+            self.0.borrow_mut().push(tx);
+            rx.map(|result| result.unwrap_or(Err(Error::Internal))).boxed()
+        }
+    }
+
+    #[test]
+    fn should_limit_requests_in_flight_across_clones() {
+        let node = Node::default();
+        let transport = RateLimited::new(
+            node.clone(),
+            RateLimits {
+                max_in_flight: Some(1),
+                ..Default::default()
+            },
+        );
+        let clone = transport.clone();
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        let mut first = transport.execute("eth_blockNumber", vec![]);
+        let mut second = clone.execute("eth_blockNumber", vec![]);
+        assert!(first.poll_unpin(&mut cx).is_pending());
+        assert!(second.poll_unpin(&mut cx).is_pending());
+        assert_eq!(node.0.borrow().len(), 1);
+
+        node.0.borrow_mut().remove(0).send(Ok(1.into())).unwrap();
+        assert_eq!(block_on(first), Ok(1.into()));
+        assert!(second.poll_unpin(&mut cx).is_pending());
+        assert_eq!(node.0.borrow().len(), 1);
+
+        drop(second);
+        let mut third = transport.execute("eth_blockNumber", vec![]);
+        assert!(third.poll_unpin(&mut cx).is_pending());
+        assert_eq!(node.0.borrow().len(), 2);
+    }
+
+    #[derive(Debug, Clone)]
+    struct ReadyNode;
+
+    impl Transport for ReadyNode {
+        type Out = future::Ready<error::Result<rpc::Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+            (1, crate::helpers::build_request(1, method, params))
+        }
+
+        fn send(&self, _id: RequestId, _request: rpc::Call) -> Self::Out {
+            future::ready(Ok(rpc::Value::Null))
+        }
+    }
+
+    fn weighted_limits() -> RateLimits {
+        RateLimits {
+            requests_per_second: Some(10),
+            method_weights: vec![("eth_getLogs".to_owned(), 5)].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_limit_weighted_request_rate() {
+        let transport = RateLimited::new(ReadyNode, weighted_limits());
+        let limiter = &transport.limiter;
+        let waker = futures::task::noop_waker();
+        let started = limiter.state.lock().updated;
+        let weight = |method| limiter.limits.weight(&transport.prepare(method, vec![]).1);
+        let release = || limiter.release();
+
+        for _ in 0..5 {
+            assert_eq!(limiter.try_acquire(weight("eth_blockNumber"), &waker, started), Ok(()));
+            release();
+        }
+        assert_eq!(limiter.try_acquire(weight("eth_getLogs"), &waker, started), Ok(()));
+        release();
+
+        // the bucket is empty and refills with 10 tokens per second
+        assert_eq!(
+            limiter.try_acquire(weight("eth_getLogs"), &waker, started + Duration::from_millis(100)),
+            Err(Some(Duration::from_millis(400)))
+        );
+        assert_eq!(
+            limiter.try_acquire(weight("eth_getLogs"), &waker, started + Duration::from_millis(500)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn should_delay_requests_exceeding_the_rate() {
+        let started = Instant::now();
+        let transport = RateLimited::new(ReadyNode, weighted_limits());
+
+        block_on(transport.execute("eth_getLogs", vec![])).unwrap();
+        block_on(transport.execute("eth_getLogs", vec![])).unwrap();
+        block_on(transport.execute("eth_getLogs", vec![])).unwrap();
+
+        // the last request waits for 5 tokens to be refilled, with some slack for the timer
+        assert!(started.elapsed() >= Duration::from_millis(400));
+    }
+}
@@ -0,0 +1,316 @@
+//! Record-and-replay Transports
+
+use std::fs;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::Arc;
+
+use crate::error::{self, Error};
+use crate::helpers;
+use crate::rpc;
+use crate::{BatchTransport, RequestId, Transport};
+use futures::future::{self, Ready};
+use futures::task::{Context, Poll};
+use futures::{Future, FutureExt};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// Response recorded for a call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Recorded {
+    /// Successful response.
+    #[serde(rename = "result")]
+    Result(rpc::Value),
+    /// Error returned by the node.
+    #[serde(rename = "error")]
+    Error(rpc::Error),
+}
+
+/// A call and its response, as stored in the cassette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Called method.
+    pub method: String,
+    /// Parameters of the call.
+    pub params: rpc::Params,
+    /// Response to the call.
+    #[serde(flatten)]
+    pub response: Recorded,
+}
+
+impl Interaction {
+    fn new(call: &rpc::Call, result: &error::Result<rpc::Value>) -> Option<Self> {
+        let call = match call {
+            rpc::Call::MethodCall(call) => call,
+            _ => return None,
+        };
+        let response = match result {
+            Ok(value) => Recorded::Result(value.clone()),
+            Err(Error::Rpc(err)) => Recorded::Error(err.clone()),
+            // Transport failures are not a part of the node's behaviour.
+            Err(_) => return None,
+        };
+        Some(Interaction {
+            method: call.method.clone(),
+            params: call.params.clone(),
+            response,
+        })
+    }
+}
+
+type Cassette = Arc<Mutex<Vec<Interaction>>>;
+
+/// Transport recording calls and responses of the inner transport, so they can be served by `Replay`.
+#[derive(Debug, Clone)]
+pub struct Recorder<T> {
+    transport: T,
+    cassette: Cassette,
+}
+
+impl<T: Transport> Recorder<T> {
+    /// Wraps given transport.
+    pub fn new(transport: T) -> Self {
+        Recorder {
+            transport,
+            cassette: Default::default(),
+        }
+    }
+
+    /// Returns interactions recorded so far.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.cassette.lock().clone()
+    }
+
+    /// Writes the recorded interactions to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> error::Result {
+        let json = serde_json::to_string_pretty(&*self.cassette.lock())?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+impl<T: Transport> Transport for Recorder<T> {
+    type Out = Recording<T::Out>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        self.transport.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        Recording {
+            future: self.transport.send(id, request.clone()),
+            calls: vec![request],
+            cassette: self.cassette.clone(),
+        }
+    }
+}
+
+impl<T: BatchTransport> BatchTransport for Recorder<T> {
+    type Batch = Recording<Pin<Box<T::Batch>>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        let requests: Vec<_> = requests.into_iter().collect();
+        let calls = requests.iter().map(|(_, call)| call.clone()).collect();
+        Recording {
+            future: Box::pin(self.transport.send_batch(requests)),
+            calls,
+            cassette: self.cassette.clone(),
+        }
+    }
+}
+
+/// Results which can be recorded.
+pub trait Recordable {
+    /// Returns the results of individual calls.
+    fn results(&self) -> Vec<&error::Result<rpc::Value>>;
+}
+
+impl Recordable for error::Result<rpc::Value> {
+    fn results(&self) -> Vec<&error::Result<rpc::Value>> {
+        vec![self]
+    }
+}
+
+impl Recordable for error::Result<Vec<error::Result<rpc::Value>>> {
+    fn results(&self) -> Vec<&error::Result<rpc::Value>> {
+        match self {
+            Ok(results) => results.iter().collect(),
+            Err(_) => vec![],
+        }
+    }
+}
+
+/// A request being recorded.
+pub struct Recording<F> {
+    future: F,
+    calls: Vec<rpc::Call>,
+    cassette: Cassette,
+}
+
+impl<F> Future for Recording<F>
+where
+    F: Future + Unpin,
+    F::Output: Recordable,
+{
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let output = ready!(self.future.poll_unpin(cx));
+        let interactions = self
+            .calls
+            .iter()
+            .zip(output.results())
+            .filter_map(|(call, result)| Interaction::new(call, result));
+        self.cassette.lock().extend(interactions);
+        Poll::Ready(output)
+    }
+}
+
+/// Transport serving responses recorded by `Recorder`, without connecting to any node.
+///
+/// Calls are matched by method and parameters. Identical calls are answered in the order they were
+/// recorded, repeating the last response once all of them were used.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    id: Arc<AtomicUsize>,
+    interactions: Arc<Vec<Interaction>>,
+    used: Arc<Mutex<Vec<bool>>>,
+}
+
+impl Replay {
+    /// Creates new transport serving given interactions.
+    pub fn new(interactions: Vec<Interaction>) -> Self {
+        Replay {
+            id: Arc::new(AtomicUsize::new(1)),
+            used: Arc::new(Mutex::new(vec![false; interactions.len()])),
+            interactions: Arc::new(interactions),
+        }
+    }
+
+    /// Loads interactions saved by `Recorder::save`.
+    pub fn load<P: AsRef<Path>>(path: P) -> error::Result<Self> {
+        let json = fs::read(path)?;
+        Ok(Self::new(serde_json::from_slice(&json)?))
+    }
+
+    fn respond(&self, call: &rpc::Call) -> error::Result<rpc::Value> {
+        let call = match call {
+            rpc::Call::MethodCall(call) => call,
+            _ => return Err(Error::Transport("Only method calls can be replayed.".into())),
+        };
+        let mut used = self.used.lock();
+        let matching: Vec<_> = self
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| interaction.method == call.method && interaction.params == call.params)
+            .map(|(idx, _)| idx)
+            .collect();
+        let next = matching.iter().find(|idx| !used[**idx]).or_else(|| matching.last());
+        let idx = *next.ok_or_else(|| {
+            Error::Transport(format!(
+                "No recorded response for {} with params {}",
+                call.method,
+                serde_json::to_string(&call.params).unwrap_or_default()
+            ))
+        })?;
+        used[idx] = true;
+        match self.interactions[idx].response {
+            Recorded::Result(ref value) => Ok(value.clone()),
+            Recorded::Error(ref err) => Err(Error::Rpc(err.clone())),
+        }
+    }
+}
+
+impl Transport for Replay {
+    type Out = Ready<error::Result<rpc::Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        let id = self.id.fetch_add(1, atomic::Ordering::AcqRel);
+        let request = helpers::build_request(id, method, params);
+
+        (id, request)
+    }
+
+    fn send(&self, _id: RequestId, request: rpc::Call) -> Self::Out {
+        future::ready(self.respond(&request))
+    }
+}
+
+impl BatchTransport for Replay {
+    type Batch = Ready<error::Result<Vec<error::Result<rpc::Value>>>>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        future::ready(Ok(requests.into_iter().map(|(_, call)| self.respond(&call)).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Recorder, Replay};
+    use crate::error::Error;
+    use crate::helpers::tests::TestTransport;
+    use crate::rpc;
+    use crate::{BatchTransport, Transport};
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    #[test]
+    fn should_replay_recorded_responses() {
+        let mut transport = TestTransport::default();
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!("0x2"));
+        transport.add_response(json!("0x10"));
+        let recorder = Recorder::new(transport);
+
+        block_on(recorder.execute("eth_blockNumber", vec![])).unwrap();
+        block_on(recorder.execute("eth_blockNumber", vec![])).unwrap();
+        block_on(recorder.execute("eth_getBalance", vec![json!("0x00"), json!("latest")])).unwrap();
+        let path = std::env::temp_dir().join(format!("web3-cassette-{}.json", std::process::id()));
+        recorder.save(&path).unwrap();
+        let replay = Replay::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            block_on(replay.execute("eth_getBalance", vec![json!("0x00"), json!("latest")])),
+            Ok(json!("0x10"))
+        );
+        assert_eq!(block_on(replay.execute("eth_blockNumber", vec![])), Ok(json!("0x1")));
+        assert_eq!(block_on(replay.execute("eth_blockNumber", vec![])), Ok(json!("0x2")));
+        assert_eq!(block_on(replay.execute("eth_blockNumber", vec![])), Ok(json!("0x2")));
+    }
+
+    #[test]
+    fn should_replay_errors_and_batches() {
+        let error = rpc::Error {
+            code: rpc::ErrorCode::ServerError(-32000),
+            message: "execution reverted".into(),
+            data: None,
+        };
+        let replay = Replay::new(
+            serde_json::from_value(json!([
+                { "method": "eth_call", "params": [{}, "0x1"], "error": error },
+                { "method": "eth_chainId", "params": [], "result": "0x1" },
+            ]))
+            .unwrap(),
+        );
+
+        let requests = vec![
+            replay.prepare("eth_chainId", vec![]),
+            replay.prepare("eth_call", vec![json!({}), json!("0x1")]),
+            replay.prepare("eth_call", vec![json!({}), json!("0x2")]),
+        ];
+        let results = block_on(replay.send_batch(requests)).unwrap();
+
+        assert_eq!(results[0], Ok(json!("0x1")));
+        assert_eq!(results[1], Err(Error::Rpc(error)));
+        assert!(results[2].is_err());
+    }
+}
@@ -0,0 +1,419 @@
+//! Retrying Transport
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::error::{self, Error};
+use crate::rpc;
+use crate::{api, BatchTransport, DuplexTransport, RequestId, Transport};
+use futures::task::{Context, Poll};
+use futures::{Future, FutureExt};
+use futures_timer::Delay;
+
+/// Conditions and delays of retrying failed requests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximal number of attempts (including the first one).
+    pub max_attempts: usize,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between attempts.
+    pub max_backoff: Duration,
+    /// Factor the delay is multiplied by after every failed attempt.
+    pub backoff_multiplier: u32,
+    /// Randomize delays (between half and full backoff) to avoid retrying in lockstep.
+    pub jitter: bool,
+    /// Retry requests failed with `Error::Unreachable`.
+    pub retry_unreachable: bool,
+    /// Retry requests failed with `Error::Transport`.
+    pub retry_transport: bool,
+    /// Codes of `Error::Rpc` errors to retry (e.g. `-32005` returned by rate-limited nodes).
+    pub retry_rpc_codes: Vec<i64>,
+    /// Methods which must not be executed twice.
+    pub non_idempotent: Vec<String>,
+    /// Retry also methods listed as non-idempotent.
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            backoff_multiplier: 2,
+            jitter: true,
+            retry_unreachable: true,
+            retry_transport: true,
+            retry_rpc_codes: vec![-32005],
+            non_idempotent: vec![
+                "eth_sendTransaction".into(),
+                "eth_sendRawTransaction".into(),
+                "personal_sendTransaction".into(),
+            ],
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns true if the request failed with given error may succeed when retried.
+    pub fn is_retryable(&self, error: &Error) -> bool {
+        match error {
+            Error::Unreachable => self.retry_unreachable,
+            Error::Transport(_) => self.retry_transport,
+            Error::Rpc(e) => self.retry_rpc_codes.contains(&e.code.code()),
+            _ => false,
+        }
+    }
+
+    /// Returns true if given calls may be sent more than once.
+    fn may_retry<'a>(&self, mut calls: impl Iterator<Item = &'a rpc::Call>) -> bool {
+        self.retry_non_idempotent
+            || calls.all(|call| match call {
+                rpc::Call::MethodCall(call) => !self.non_idempotent.contains(&call.method),
+                rpc::Call::Notification(call) => !self.non_idempotent.contains(&call.method),
+                rpc::Call::Invalid { .. } => false,
+            })
+    }
+
+    /// Returns the delay before given (zero-based) retry.
+    fn backoff(&self, retry: usize) -> Duration {
+        let mut delay = self.initial_backoff;
+        for _ in 0..retry {
+            delay = match delay.checked_mul(self.backoff_multiplier) {
+                Some(delay) if delay < self.max_backoff => delay,
+                _ => self.max_backoff,
+            };
+        }
+        let delay = std::cmp::min(delay, self.max_backoff);
+        if self.jitter {
+            let half = delay / 2;
+            half + random_fraction(delay - half)
+        } else {
+            delay
+        }
+    }
+}
+
+/// Returns a pseudo-random duration between zero and `max`.
+fn random_fraction(max: Duration) -> Duration {
+    let nanos = max.as_nanos() as u64;
+    if nanos == 0 {
+        return max;
+    }
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(now) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    Duration::from_nanos(hasher.finish() % (nanos + 1))
+}
+
+/// Transport retrying requests which failed with transient errors.
+///
+/// Batches are retried only when the whole batch fails, never because of errors of individual calls.
+#[derive(Debug, Clone)]
+pub struct Retry<T> {
+    transport: T,
+    policy: Arc<RetryPolicy>,
+}
+
+impl<T: Transport> Retry<T> {
+    /// Wraps given transport, retrying according to the default policy.
+    pub fn new(transport: T) -> Self {
+        Self::with_policy(transport, Default::default())
+    }
+
+    /// Wraps given transport, retrying according to given policy.
+    pub fn with_policy(transport: T, policy: RetryPolicy) -> Self {
+        Retry {
+            transport,
+            policy: Arc::new(policy),
+        }
+    }
+
+    /// Returns a reference to the inner transport.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+}
+
+impl<T: Transport> Transport for Retry<T> {
+    type Out = RetryFuture<T, (RequestId, rpc::Call), T::Out>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        self.transport.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        let retry = self.policy.may_retry(std::iter::once(&request));
+        RetryFuture::new(
+            self.transport.clone(),
+            self.policy.clone(),
+            retry,
+            (id, request),
+            |transport, (id, request)| transport.send(*id, request.clone()),
+        )
+    }
+}
+
+impl<T: BatchTransport> BatchTransport for Retry<T> {
+    type Batch = RetryFuture<T, Vec<(RequestId, rpc::Call)>, T::Batch>;
+
+    fn send_batch<I>(&self, requests: I) -> Self::Batch
+    where
+        I: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        let requests: Vec<_> = requests.into_iter().collect();
+        let retry = self.policy.may_retry(requests.iter().map(|(_, call)| call));
+        RetryFuture::new(
+            self.transport.clone(),
+            self.policy.clone(),
+            retry,
+            requests,
+            |transport, requests| transport.send_batch(requests.clone()),
+        )
+    }
+}
+
+impl<T: DuplexTransport> DuplexTransport for Retry<T> {
+    type NotificationStream = T::NotificationStream;
+
+    fn subscribe(&self, id: api::SubscriptionId) -> error::Result<Self::NotificationStream> {
+        self.transport.subscribe(id)
+    }
+
+    fn unsubscribe(&self, id: api::SubscriptionId) -> error::Result {
+        self.transport.unsubscribe(id)
+    }
+}
+
+enum RetryState<F> {
+    Sending(Pin<Box<F>>),
+    Waiting(Delay),
+}
+
+/// A request being retried.
+pub struct RetryFuture<T, R, F> {
+    transport: T,
+    policy: Arc<RetryPolicy>,
+    retry: bool,
+    request: R,
+    send: fn(&T, &R) -> F,
+    attempt: usize,
+    state: RetryState<F>,
+}
+
+impl<T, R, F> RetryFuture<T, R, F> {
+    fn new(transport: T, policy: Arc<RetryPolicy>, retry: bool, request: R, send: fn(&T, &R) -> F) -> Self {
+        let state = RetryState::Sending(Box::pin(send(&transport, &request)));
+        RetryFuture {
+            transport,
+            policy,
+            retry,
+            request,
+            send,
+            attempt: 1,
+            state,
+        }
+    }
+}
+
+impl<T, R, F, O> Future for RetryFuture<T, R, F>
+where
+    T: Unpin,
+    R: Unpin,
+    F: Future<Output = error::Result<O>>,
+{
+    type Output = error::Result<O>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        loop {
+            let this = &mut *self;
+            match this.state {
+                RetryState::Sending(ref mut future) => match ready!(future.poll_unpin(cx)) {
+                    Err(ref err)
+                        if this.retry && this.attempt < this.policy.max_attempts && this.policy.is_retryable(err) =>
+                    {
+                        let delay = this.policy.backoff(this.attempt - 1);
+                        log::debug!("Request failed ({:?}), retrying in {:?}", err, delay);
+                        this.attempt += 1;
+                        this.state = RetryState::Waiting(Delay::new(delay));
+                    }
+                    result => return Poll::Ready(result),
+                },
+                RetryState::Waiting(ref mut delay) => {
+                    ready!(delay.poll_unpin(cx));
+                    this.state = RetryState::Sending(Box::pin((this.send)(&this.transport, &this.request)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Retry, RetryPolicy};
+    use crate::error::{self, Error};
+    use crate::rpc;
+    use crate::{BatchTransport, RequestId, Transport};
+    use futures::future;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[derive(Debug, Default, Clone)]
+    struct FlakyTransport {
+        errors: Rc<RefCell<VecDeque<Error>>>,
+        requests: Rc<RefCell<usize>>,
+    }
+
+    impl FlakyTransport {
+        fn failing(errors: Vec<Error>) -> Self {
+            FlakyTransport {
+                errors: Rc::new(RefCell::new(errors.into())),
+                requests: Default::default(),
+            }
+        }
+
+        fn result<T>(&self, value: T) -> future::Ready<error::Result<T>> {
+            *self.requests.borrow_mut() += 1;
+            future::ready(match self.errors.borrow_mut().pop_front() {
+                Some(err) => Err(err),
+                None => Ok(value),
+            })
+        }
+    }
+
+    impl Transport for FlakyTransport {
+        type Out = future::Ready<error::Result<rpc::Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+            (1, crate::helpers::build_request(1, method, params))
+        }
+
+        fn send(&self, _id: RequestId, _request: rpc::Call) -> Self::Out {
+            self.result(rpc::Value::Bool(true))
+        }
+    }
+
+    impl BatchTransport for FlakyTransport {
+        type Batch = future::Ready<error::Result<Vec<error::Result<rpc::Value>>>>;
+
+        fn send_batch<T>(&self, requests: T) -> Self::Batch
+        where
+            T: IntoIterator<Item = (RequestId, rpc::Call)>,
+        {
+            let results = requests.into_iter().map(|_| Ok(rpc::Value::Bool(true))).collect();
+            self.result(results)
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(0),
+            jitter: false,
+            ..Default::default()
+        }
+    }
+
+    fn rate_limited() -> Error {
+        Error::Rpc(rpc::Error {
+            code: rpc::ErrorCode::ServerError(-32005),
+            message: "limit exceeded".into(),
+            data: None,
+        })
+    }
+
+    #[test]
+    fn should_retry_transient_errors() {
+        let transport = FlakyTransport::failing(vec![Error::Unreachable, rate_limited()]);
+        let retry = Retry::with_policy(transport.clone(), policy());
+
+        let result = futures::executor::block_on(retry.execute("eth_blockNumber", vec![]));
+
+        assert_eq!(result, Ok(rpc::Value::Bool(true)));
+        assert_eq!(*transport.requests.borrow(), 3);
+    }
+
+    #[test]
+    fn should_give_up_after_max_attempts() {
+        let transport = FlakyTransport::failing(vec![Error::Unreachable; 3]);
+        let retry = Retry::with_policy(transport.clone(), policy());
+
+        let result = futures::executor::block_on(retry.execute("eth_blockNumber", vec![]));
+
+        assert_eq!(result, Err(Error::Unreachable));
+        assert_eq!(*transport.requests.borrow(), 3);
+    }
+
+    #[test]
+    fn should_not_retry_permanent_errors() {
+        let transport = FlakyTransport::failing(vec![Error::InvalidResponse("x".into())]);
+        let retry = Retry::with_policy(transport.clone(), policy());
+
+        let result = futures::executor::block_on(retry.execute("eth_blockNumber", vec![]));
+
+        assert_eq!(result, Err(Error::InvalidResponse("x".into())));
+        assert_eq!(*transport.requests.borrow(), 1);
+    }
+
+    #[test]
+    fn should_not_retry_non_idempotent_methods() {
+        let transport = FlakyTransport::failing(vec![Error::Unreachable]);
+        let retry = Retry::with_policy(transport.clone(), policy());
+
+        let result = futures::executor::block_on(retry.execute("eth_sendTransaction", vec![]));
+
+        assert_eq!(result, Err(Error::Unreachable));
+        assert_eq!(*transport.requests.borrow(), 1);
+
+        let retry = Retry::with_policy(
+            transport.clone(),
+            RetryPolicy {
+                retry_non_idempotent: true,
+                ..policy()
+            },
+        );
+        transport.errors.borrow_mut().push_back(Error::Unreachable);
+
+        let result = futures::executor::block_on(retry.execute("eth_sendTransaction", vec![]));
+
+        assert_eq!(result, Ok(rpc::Value::Bool(true)));
+        assert_eq!(*transport.requests.borrow(), 3);
+    }
+
+    #[test]
+    fn should_retry_failed_batch() {
+        let transport = FlakyTransport::failing(vec![Error::Transport("x".into())]);
+        let retry = Retry::with_policy(transport.clone(), policy());
+        let requests = vec![
+            retry.prepare("eth_blockNumber", vec![]),
+            retry.prepare("eth_gasPrice", vec![]),
+        ];
+
+        let result = futures::executor::block_on(retry.send_batch(requests));
+
+        assert_eq!(result, Ok(vec![Ok(rpc::Value::Bool(true)), Ok(rpc::Value::Bool(true))]));
+        assert_eq!(*transport.requests.borrow(), 2);
+    }
+
+    #[test]
+    fn should_randomize_backoff() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(3),
+            ..Default::default()
+        };
+
+        for retry in 0..5 {
+            let max = std::cmp::min(Duration::from_secs(1 << retry), Duration::from_secs(3));
+            let delay = policy.backoff(retry);
+            assert!(delay >= max / 2 && delay <= max, "{:?} not within {:?}", delay, max);
+        }
+    }
+}
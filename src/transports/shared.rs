@@ -0,0 +1,425 @@
+//! Request and subscription bookkeeping shared by the duplex (WS & IPC) transports.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::marker::Unpin;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::api::SubscriptionId;
+use crate::error;
+use crate::helpers;
+use crate::rpc;
+use crate::{Error, RequestId};
+use futures::channel::oneshot;
+use futures::{
+    future,
+    task::{Context, Poll, Waker},
+    Future, FutureExt, Stream,
+};
+use parking_lot::Mutex;
+
+pub type SingleResult = error::Result<rpc::Value>;
+pub type BatchResult = error::Result<Vec<SingleResult>>;
+pub type Pending = oneshot::Sender<BatchResult>;
+pub type Subscription = NotificationSender;
+
+/// A message sent from the transport handle to the background task.
+pub enum TransportMessage {
+    Request {
+        id: RequestId,
+        request: rpc::Request,
+        sender: oneshot::Sender<BatchResult>,
+    },
+    Subscribe {
+        id: SubscriptionId,
+        sink: NotificationSender,
+    },
+    Unsubscribe {
+        id: SubscriptionId,
+    },
+}
+
+/// A parsed incoming message.
+pub enum Message {
+    /// A notification for given subscription.
+    Notification(SubscriptionId, rpc::Value),
+    /// A response to the request with given id.
+    Response(RequestId, BatchResult),
+}
+
+/// Parses a single incoming message, returns `None` if the message is not supported.
+pub fn parse_message(data: &[u8]) -> Option<Message> {
+    log::trace!("Message received: {:?}", data);
+    if let Ok(notification) = helpers::to_notification_from_slice(data) {
+        if let rpc::Params::Map(params) = notification.params {
+            let id = params.get("subscription");
+            let result = params.get("result");
+
+            if let (Some(&rpc::Value::String(ref id)), Some(result)) = (id, result) {
+                return Some(Message::Notification(id.clone().into(), result.clone()));
+            } else {
+                log::error!("Got unsupported notification (id: {:?})", id);
+            }
+        }
+        None
+    } else {
+        let response = helpers::to_response_from_slice(data);
+        let outputs = match response {
+            Ok(rpc::Response::Single(output)) => vec![output],
+            Ok(rpc::Response::Batch(outputs)) => outputs,
+            _ => vec![],
+        };
+
+        let id = match outputs.get(0) {
+            Some(&rpc::Output::Success(ref success)) => success.id.clone(),
+            Some(&rpc::Output::Failure(ref failure)) => failure.id.clone(),
+            None => rpc::Id::Num(0),
+        };
+
+        if let rpc::Id::Num(num) = id {
+            log::trace!("Response to (id: {:?}): {:?}", num, outputs);
+            Some(Message::Response(
+                num as usize,
+                helpers::to_results_from_outputs(outputs),
+            ))
+        } else {
+            log::warn!("Got unsupported response (id: {:?})", id);
+            None
+        }
+    }
+}
+
+/// Dispatches a parsed message either to a subscription or to a pending request.
+///
+/// Waits if the subscription buffer is full and configured with `NotificationOverflow::Wait`.
+pub async fn dispatch_message(
+    message: Message,
+    subscriptions: &BTreeMap<SubscriptionId, Subscription>,
+    pending: &mut BTreeMap<RequestId, Pending>,
+) {
+    match message {
+        Message::Notification(id, result) => {
+            if let Some(stream) = subscriptions.get(&id) {
+                if let Err(e) = stream.send(result).await {
+                    log::error!("Error sending notification: {:?} (id: {:?}", e, id);
+                }
+            } else {
+                log::warn!("Got notification for unknown subscription (id: {:?})", id);
+            }
+        }
+        Message::Response(id, result) => {
+            if let Some(request) = pending.remove(&id) {
+                if let Err(err) = request.send(result) {
+                    log::warn!("Sending a response to deallocated channel: {:?}", err);
+                }
+            } else {
+                log::warn!("Got response for unknown request (id: {:?})", id);
+            }
+        }
+    }
+}
+
+pub fn dropped_err<T>(_: T) -> error::Error {
+    Error::Transport("Cannot send request. Internal task finished.".into())
+}
+
+pub fn batch_to_single(response: BatchResult) -> SingleResult {
+    match response?.into_iter().next() {
+        Some(res) => res,
+        None => Err(Error::InvalidResponse("Expected single, got batch.".into())),
+    }
+}
+
+pub fn batch_to_batch(res: BatchResult) -> BatchResult {
+    res
+}
+
+type QueuedRequest = Pin<Box<dyn Future<Output = error::Result<oneshot::Receiver<BatchResult>>> + Send>>;
+
+#[cfg_attr(not(any(feature = "ws-tokio", feature = "ws-async-std")), allow(dead_code))]
+enum ResponseState {
+    Queued(QueuedRequest),
+    Receiver(Option<error::Result<oneshot::Receiver<BatchResult>>>),
+    Waiting(oneshot::Receiver<BatchResult>),
+}
+
+/// A response wrapper of the duplex transports.
+pub struct Response<R, T> {
+    extract: T,
+    state: ResponseState,
+    _data: std::marker::PhantomData<R>,
+}
+
+impl<R, T> Response<R, T> {
+    pub(crate) fn new(response: error::Result<oneshot::Receiver<BatchResult>>, extract: T) -> Self {
+        Self {
+            extract,
+            state: ResponseState::Receiver(Some(response)),
+            _data: Default::default(),
+        }
+    }
+
+    /// Creates a response which needs to wait for the request to be queued first.
+    #[cfg_attr(not(any(feature = "ws-tokio", feature = "ws-async-std")), allow(dead_code))]
+    pub(crate) fn queued(request: QueuedRequest, extract: T) -> Self {
+        Self {
+            extract,
+            state: ResponseState::Queued(request),
+            _data: Default::default(),
+        }
+    }
+}
+
+impl<R, T> Future for Response<R, T>
+where
+    R: Unpin + 'static,
+    T: Fn(BatchResult) -> error::Result<R> + Unpin + 'static,
+{
+    type Output = error::Result<R>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        loop {
+            match self.state {
+                ResponseState::Queued(ref mut request) => {
+                    let receiver = ready!(request.poll_unpin(cx));
+                    self.state = ResponseState::Receiver(Some(receiver));
+                }
+                ResponseState::Receiver(ref mut res) => {
+                    let receiver = res.take().expect("Receiver state is active only once; qed")?;
+                    self.state = ResponseState::Waiting(receiver)
+                }
+                ResponseState::Waiting(ref mut future) => {
+                    let response = ready!(future.poll_unpin(cx)).map_err(dropped_err)?;
+                    return Poll::Ready((self.extract)(response));
+                }
+            }
+        }
+    }
+}
+
+/// Behaviour of a subscription buffer when it's full.
+#[cfg_attr(not(any(feature = "ws-tokio", feature = "ws-async-std")), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationOverflow {
+    /// Stop reading from the connection until the subscriber consumes a notification.
+    ///
+    /// Note that it delays responses to all other requests as well.
+    Wait,
+    /// Close the subscription. The stream ends after yielding the already buffered notifications.
+    Close,
+    /// Drop the oldest buffered notification (see `NotificationStream::lagged`).
+    DropOldest,
+}
+
+struct NotificationQueue {
+    items: VecDeque<rpc::Value>,
+    lagged: usize,
+    closed: bool,
+    receiver: Option<Waker>,
+    sender: Option<Waker>,
+}
+
+impl NotificationQueue {
+    fn close(&mut self) {
+        self.closed = true;
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        for waker in self.receiver.take().into_iter().chain(self.sender.take()) {
+            waker.wake();
+        }
+    }
+}
+
+/// Creates a new subscription buffer, optionally limited to given capacity.
+pub fn notification_channel(limit: Option<(usize, NotificationOverflow)>) -> (NotificationSender, NotificationStream) {
+    let queue = Arc::new(Mutex::new(NotificationQueue {
+        items: Default::default(),
+        lagged: 0,
+        closed: false,
+        receiver: None,
+        sender: None,
+    }));
+    let limit = limit.map(|(capacity, overflow)| (std::cmp::max(capacity, 1), overflow));
+    (
+        NotificationSender {
+            queue: queue.clone(),
+            limit,
+        },
+        NotificationStream { queue },
+    )
+}
+
+/// Sending half of a subscription buffer.
+pub struct NotificationSender {
+    queue: Arc<Mutex<NotificationQueue>>,
+    limit: Option<(usize, NotificationOverflow)>,
+}
+
+impl NotificationSender {
+    /// Pushes the notification to the subscription, returns it back if the subscription is closed.
+    pub async fn send(&self, value: rpc::Value) -> Result<(), rpc::Value> {
+        let mut value = Some(value);
+        future::poll_fn(|cx| {
+            let mut queue = self.queue.lock();
+            let mut take = || value.take().expect("Value is taken only once; qed");
+            if queue.closed {
+                return Poll::Ready(Err(take()));
+            }
+            if let Some((capacity, overflow)) = self.limit {
+                if queue.items.len() >= capacity {
+                    match overflow {
+                        NotificationOverflow::Wait => {
+                            queue.sender = Some(cx.waker().clone());
+                            return Poll::Pending;
+                        }
+                        NotificationOverflow::Close => {
+                            log::warn!("Subscription buffer is full, closing the subscription.");
+                            queue.close();
+                            return Poll::Ready(Err(take()));
+                        }
+                        NotificationOverflow::DropOldest => {
+                            queue.items.pop_front();
+                            queue.lagged += 1;
+                        }
+                    }
+                }
+            }
+            queue.items.push_back(take());
+            queue.wake();
+            Poll::Ready(Ok(()))
+        })
+        .await
+    }
+}
+
+impl Drop for NotificationSender {
+    fn drop(&mut self) {
+        self.queue.lock().close();
+    }
+}
+
+/// Stream of notifications of a single subscription.
+pub struct NotificationStream {
+    queue: Arc<Mutex<NotificationQueue>>,
+}
+
+impl NotificationStream {
+    /// Returns the number of notifications dropped so far, because the buffer was full.
+    ///
+    /// Always zero unless the buffer is configured with `NotificationOverflow::DropOldest`.
+    pub fn lagged(&self) -> usize {
+        self.queue.lock().lagged
+    }
+}
+
+impl fmt::Debug for NotificationStream {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let queue = self.queue.lock();
+        fmt.debug_struct("NotificationStream")
+            .field("buffered", &queue.items.len())
+            .field("lagged", &queue.lagged)
+            .field("closed", &queue.closed)
+            .finish()
+    }
+}
+
+impl Stream for NotificationStream {
+    type Item = rpc::Value;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let mut queue = self.queue.lock();
+        if let Some(item) = queue.items.pop_front() {
+            if let Some(waker) = queue.sender.take() {
+                waker.wake();
+            }
+            return Poll::Ready(Some(item));
+        }
+        if queue.closed {
+            return Poll::Ready(None);
+        }
+        queue.receiver = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for NotificationStream {
+    fn drop(&mut self) {
+        let mut queue = self.queue.lock();
+        queue.close();
+        // Nobody is going to read them anymore.
+        queue.items.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn should_drop_oldest_notifications() {
+        // given
+        let (sink, mut stream) = notification_channel(Some((2, NotificationOverflow::DropOldest)));
+
+        // when
+        for i in 0..5 {
+            futures::executor::block_on(sink.send(i.into())).unwrap();
+        }
+        drop(sink);
+
+        // then
+        assert_eq!(stream.lagged(), 3);
+        let received: Vec<_> = futures::executor::block_on_stream(&mut stream).collect();
+        assert_eq!(received, vec![rpc::Value::from(3), rpc::Value::from(4)]);
+    }
+
+    #[test]
+    fn should_close_subscription_when_buffer_is_full() {
+        // given
+        let (sink, mut stream) = notification_channel(Some((1, NotificationOverflow::Close)));
+        futures::executor::block_on(sink.send(1.into())).unwrap();
+
+        // when
+        let res = futures::executor::block_on(sink.send(2.into()));
+
+        // then
+        assert_eq!(res, Err(rpc::Value::from(2)));
+        assert_eq!(futures::executor::block_on(stream.next()), Some(1.into()));
+        assert_eq!(futures::executor::block_on(stream.next()), None);
+    }
+
+    #[test]
+    fn should_wait_until_notification_is_consumed() {
+        // given
+        let (sink, mut stream) = notification_channel(Some((1, NotificationOverflow::Wait)));
+        futures::executor::block_on(sink.send(1.into())).unwrap();
+        let mut send = Box::pin(sink.send(2.into()));
+        assert!(futures::executor::block_on(async { futures::poll!(&mut send) }).is_pending());
+
+        // when
+        let first = futures::executor::block_on(stream.next());
+
+        // then
+        assert_eq!(first, Some(1.into()));
+        assert_eq!(futures::executor::block_on(send), Ok(()));
+        assert_eq!(futures::executor::block_on(stream.next()), Some(2.into()));
+    }
+
+    #[test]
+    fn should_stop_waiting_when_stream_is_dropped() {
+        // given
+        let (sink, stream) = notification_channel(Some((1, NotificationOverflow::Wait)));
+        futures::executor::block_on(sink.send(1.into())).unwrap();
+
+        // when
+        drop(stream);
+
+        // then
+        assert_eq!(
+            futures::executor::block_on(sink.send(2.into())),
+            Err(rpc::Value::from(2))
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! TLS configuration shared by the HTTP and WebSocket transports.
+
+use std::fmt;
+
+use crate::error;
+
+#[derive(Clone)]
+enum Certificate {
+    Pem(Vec<u8>),
+    Der(Vec<u8>),
+}
+
+#[derive(Clone)]
+enum Identity {
+    Pkcs12 { der: Vec<u8>, password: String },
+    Pkcs8 { pem: Vec<u8>, key: Vec<u8> },
+}
+
+/// Custom TLS settings of a transport.
+///
+/// Certificates and identities are validated when the transport is built.
+#[derive(Clone, Default)]
+pub struct TlsConfig {
+    root_certificates: Vec<Certificate>,
+    disable_built_in_roots: bool,
+    identity: Option<Identity>,
+    domain: Option<String>,
+}
+
+impl TlsConfig {
+    /// Create TLS configuration with system defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust given PEM-encoded root certificate in addition to the system ones.
+    pub fn add_root_certificate_pem(mut self, pem: &[u8]) -> Self {
+        self.root_certificates.push(Certificate::Pem(pem.into()));
+        self
+    }
+
+    /// Trust given DER-encoded root certificate in addition to the system ones.
+    pub fn add_root_certificate_der(mut self, der: &[u8]) -> Self {
+        self.root_certificates.push(Certificate::Der(der.into()));
+        self
+    }
+
+    /// Trust only the root certificates added explicitly.
+    pub fn disable_built_in_roots(mut self, disable: bool) -> Self {
+        self.disable_built_in_roots = disable;
+        self
+    }
+
+    /// Authenticate with a client certificate from a DER-encoded PKCS #12 archive.
+    pub fn identity_pkcs12(mut self, der: &[u8], password: &str) -> Self {
+        self.identity = Some(Identity::Pkcs12 {
+            der: der.into(),
+            password: password.into(),
+        });
+        self
+    }
+
+    /// Authenticate with a PEM-encoded client certificate chain and PKCS #8 private key.
+    pub fn identity_pkcs8(mut self, pem: &[u8], key: &[u8]) -> Self {
+        self.identity = Some(Identity::Pkcs8 {
+            pem: pem.into(),
+            key: key.into(),
+        });
+        self
+    }
+
+    /// Use given name for SNI and certificate validation instead of the host from the URL.
+    ///
+    /// Useful when connecting to the server by IP address.
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Returns the name to verify the server certificate against, if different from the host.
+    pub(crate) fn domain_override(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    /// Creates a connector builder with the configured certificates.
+    pub(crate) fn builder(&self) -> error::Result<native_tls::TlsConnectorBuilder> {
+        let mut builder = native_tls::TlsConnector::builder();
+        for certificate in &self.root_certificates {
+            let certificate = match certificate {
+                Certificate::Pem(pem) => native_tls::Certificate::from_pem(pem)?,
+                Certificate::Der(der) => native_tls::Certificate::from_der(der)?,
+            };
+            builder.add_root_certificate(certificate);
+        }
+        builder.disable_built_in_roots(self.disable_built_in_roots);
+        if let Some(ref identity) = self.identity {
+            let identity = match identity {
+                Identity::Pkcs12 { der, password } => native_tls::Identity::from_pkcs12(der, password)?,
+                Identity::Pkcs8 { pem, key } => native_tls::Identity::from_pkcs8(pem, key)?,
+            };
+            builder.identity(identity);
+        }
+        Ok(builder)
+    }
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TlsConfig")
+            .field("root_certificates", &self.root_certificates.len())
+            .field("disable_built_in_roots", &self.disable_built_in_roots)
+            .field("identity", &self.identity.is_some())
+            .field("domain", &self.domain)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TlsConfig;
+
+    const CA: &[u8] = include_bytes!("res/ca.pem");
+    const CLIENT_P12: &[u8] = include_bytes!("res/client.p12");
+    const CLIENT_PEM: &[u8] = include_bytes!("res/client.pem");
+    const CLIENT_KEY: &[u8] = include_bytes!("res/client-key.pem");
+
+    #[test]
+    fn should_load_certificates_and_identities() {
+        let config = TlsConfig::new().add_root_certificate_pem(CA);
+        assert!(config.clone().identity_pkcs12(CLIENT_P12, "web3").builder().is_ok());
+        assert!(config.identity_pkcs8(CLIENT_PEM, CLIENT_KEY).builder().is_ok());
+    }
+
+    #[test]
+    fn should_reject_invalid_certificates() {
+        assert!(TlsConfig::new().add_root_certificate_pem(b"invalid").builder().is_err());
+        assert!(TlsConfig::new().add_root_certificate_der(CA).builder().is_err());
+        assert!(TlsConfig::new().identity_pkcs12(CLIENT_P12, "wrong").builder().is_err());
+    }
+
+    #[test]
+    fn should_override_domain() {
+        assert_eq!(TlsConfig::new().domain_override(), None);
+        assert_eq!(
+            TlsConfig::new().domain("localhost").domain_override(),
+            Some("localhost")
+        );
+    }
+}
@@ -1,11 +1,18 @@
 //! WebSocket Transport
 
 use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::marker::Unpin;
 use std::sync::{atomic, Arc};
+use std::time::{Duration, Instant};
 use std::{fmt, pin::Pin};
 
 use self::compat::{TcpStream, TlsStream};
+use super::proxy::{self, Proxy};
+use super::shared::{
+    batch_to_batch, batch_to_single, dispatch_message, dropped_err, notification_channel, parse_message, BatchResult,
+    Message, Pending, SingleResult, Subscription, TransportMessage,
+};
 use crate::api::SubscriptionId;
 use crate::error;
 use crate::helpers;
@@ -13,10 +20,18 @@ use crate::rpc;
 use crate::{BatchTransport, DuplexTransport, Error, RequestId, Transport};
 use futures::channel::{mpsc, oneshot};
 use futures::{
+    future::Fuse,
+    stream::FusedStream,
     task::{Context, Poll},
-    Future, FutureExt, Stream, StreamExt,
+    FutureExt, SinkExt, Stream, StreamExt,
 };
 use futures::{AsyncRead, AsyncWrite};
+use futures_timer::Delay;
+
+pub use super::shared::{NotificationOverflow, NotificationStream, Response};
+
+#[cfg(any(feature = "ws-tls-tokio", feature = "ws-tls-async-std"))]
+use super::TlsConfig;
 
 use soketto::connection;
 use soketto::handshake::{Client, ServerResponse};
@@ -34,11 +49,6 @@ impl From<connection::Error> for Error {
     }
 }
 
-type SingleResult = error::Result<rpc::Value>;
-type BatchResult = error::Result<Vec<SingleResult>>;
-type Pending = oneshot::Sender<BatchResult>;
-type Subscription = mpsc::UnboundedSender<rpc::Value>;
-
 /// Stream, either plain TCP or TLS.
 enum MaybeTlsStream<P, T> {
     /// Unencrypted socket stream.
@@ -88,18 +98,606 @@ where
     }
 }
 
+type WsSender = connection::Sender<MaybeTlsStream<TcpStream, TlsStream>>;
+type WsReceiver = connection::Receiver<MaybeTlsStream<TcpStream, TlsStream>>;
+
+/// Re-connection policy of the WebSocket transport.
+///
+/// When the connection is lost the transport re-dials the same URL. It waits `initial_backoff`
+/// before the first attempt and multiplies the delay by `backoff_multiplier` after every
+/// failed one (never waiting longer than `max_backoff`).
+///
+/// Subscriptions created with `eth_subscribe` are re-issued on the new connection
+/// and keep delivering notifications under the `SubscriptionId` returned initially.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Maximal number of consecutive attempts before giving up (`None` to retry forever).
+    pub max_attempts: Option<usize>,
+    /// Delay before the first attempt.
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between attempts.
+    pub max_backoff: Duration,
+    /// Factor the delay is multiplied by after every failed attempt.
+    pub backoff_multiplier: u32,
+    /// Re-send requests which were awaiting a response when the connection was lost.
+    ///
+    /// Disabled by default, since it may cause non-idempotent calls (e.g. `eth_sendTransaction`)
+    /// to be executed twice. When disabled, such requests fail with `Error::Transport`.
+    pub retry_requests: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            max_attempts: Some(10),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            backoff_multiplier: 2,
+            retry_requests: false,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Returns the delay before given (zero-based) attempt.
+    fn backoff(&self, attempt: usize) -> Duration {
+        let mut delay = self.initial_backoff;
+        for _ in 0..attempt {
+            delay = match delay.checked_mul(self.backoff_multiplier) {
+                Some(delay) if delay < self.max_backoff => delay,
+                _ => return self.max_backoff,
+            };
+        }
+        std::cmp::min(delay, self.max_backoff)
+    }
+}
+
+/// Keep-alive settings of the WebSocket transport.
+///
+/// A ping frame is sent after `interval` without any frame received from the server.
+/// If no frame arrives within `timeout` after the ping, the connection is considered dead:
+/// pending requests fail with `Error::Transport` and the connection is handled as lost
+/// (re-connecting if a `ReconnectPolicy` is configured, or ending all subscription streams otherwise).
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeepAlive {
+    /// Time without any frame received after which a ping is sent.
+    pub interval: Duration,
+    /// Time to wait for a pong (or any other frame) after sending a ping.
+    pub timeout: Duration,
+}
+
+impl Default for KeepAlive {
+    fn default() -> Self {
+        KeepAlive {
+            interval: Duration::from_secs(30),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Keep-alive state of the current connection.
+struct Heartbeat {
+    policy: Option<KeepAlive>,
+    timer: Fuse<Delay>,
+    awaiting_pong: bool,
+}
+
+impl Heartbeat {
+    fn new(policy: Option<KeepAlive>) -> Self {
+        let mut heartbeat = Heartbeat {
+            policy,
+            timer: Fuse::terminated(),
+            awaiting_pong: false,
+        };
+        heartbeat.reset();
+        heartbeat
+    }
+
+    /// Restarts the interval after a frame was received.
+    fn reset(&mut self) {
+        self.awaiting_pong = false;
+        if let Some(ref policy) = self.policy {
+            self.timer = Delay::new(policy.interval).fuse();
+        }
+    }
+}
+
+/// Reason of losing the connection.
+enum Disconnect {
+    Error(connection::Error),
+    PongTimeout(Duration),
+}
+
+impl From<connection::Error> for Disconnect {
+    fn from(err: connection::Error) -> Self {
+        Disconnect::Error(err)
+    }
+}
+
+impl fmt::Display for Disconnect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Disconnect::Error(e) => write!(f, "{:?}", e),
+            Disconnect::PongTimeout(timeout) => write!(f, "No pong received within {:?}", timeout),
+        }
+    }
+}
+
+impl Disconnect {
+    /// Returns the error pending requests fail with.
+    fn error(&self) -> Error {
+        match self {
+            Disconnect::Error(_) => connection_lost(),
+            Disconnect::PongTimeout(timeout) => Error::Transport(format!(
+                "WS connection timed out: no pong received within {:?}.",
+                timeout
+            )),
+        }
+    }
+}
+
+/// Time after which requests without response fail.
+#[derive(Debug, Clone, Default)]
+struct RequestTimeouts {
+    default: Option<Duration>,
+    methods: BTreeMap<String, Duration>,
+}
+
+impl RequestTimeouts {
+    /// Returns the timeout of given request (the longest one of calls in a batch).
+    fn get(&self, request: &rpc::Request) -> Option<Duration> {
+        let timeout = |call: &rpc::Call| match call {
+            rpc::Call::MethodCall(call) => self.methods.get(&call.method).copied().or(self.default),
+            _ => self.default,
+        };
+        match request {
+            rpc::Request::Single(call) => timeout(call),
+            rpc::Request::Batch(calls) => calls.iter().map(timeout).max().flatten(),
+        }
+    }
+}
+
+/// A subscription which is re-created after re-connecting.
+struct Restorable {
+    /// Parameters of the original `eth_subscribe` call.
+    params: rpc::Params,
+    /// Id of the subscription assigned by the currently connected server.
+    server_id: String,
+}
+
+/// Server the transport connects to.
+#[derive(Debug, Clone)]
+struct Endpoint {
+    url: Url,
+    proxy: Option<Proxy>,
+    max_redirects: usize,
+    #[cfg(any(feature = "ws-tls-tokio", feature = "ws-tls-async-std"))]
+    tls: Option<TlsConfig>,
+}
+
 struct WsServerTask {
+    endpoint: Endpoint,
+    reconnect: Option<ReconnectPolicy>,
+    keepalive: Option<KeepAlive>,
+    timeouts: RequestTimeouts,
+    id: Arc<atomic::AtomicUsize>,
     pending: BTreeMap<RequestId, Pending>,
+    /// Deadlines of pending requests with a timeout.
+    deadlines: BTreeMap<(Instant, RequestId), Duration>,
+    /// Deadlines of pending requests, by request id.
+    request_deadlines: BTreeMap<RequestId, Instant>,
     subscriptions: BTreeMap<SubscriptionId, Subscription>,
-    sender: connection::Sender<MaybeTlsStream<TcpStream, TlsStream>>,
-    receiver: connection::Receiver<MaybeTlsStream<TcpStream, TlsStream>>,
+    /// Serialized requests awaiting response (only if they are re-sent after re-connecting).
+    in_flight: BTreeMap<RequestId, String>,
+    /// `eth_subscribe` calls awaiting response.
+    subscribe_requests: BTreeMap<RequestId, rpc::Params>,
+    /// Active subscriptions, by the id known to the caller.
+    restorable: BTreeMap<SubscriptionId, Restorable>,
+    /// Re-issued `eth_subscribe` calls awaiting response.
+    resubscribe_requests: BTreeMap<RequestId, SubscriptionId>,
+    /// Subscription ids assigned after re-connecting, mapped to the ones known to the caller.
+    aliases: BTreeMap<SubscriptionId, SubscriptionId>,
+    /// Server ids of subscriptions which are no longer consumed, but weren't unsubscribed yet.
+    retired: BTreeMap<SubscriptionId, String>,
 }
 
 impl WsServerTask {
     /// Create new WebSocket transport.
-    pub async fn new(url: &str) -> error::Result<Self> {
-        let url = Url::parse(url)?;
+    pub async fn new(
+        endpoint: Endpoint,
+        reconnect: Option<ReconnectPolicy>,
+        keepalive: Option<KeepAlive>,
+        timeouts: RequestTimeouts,
+        id: Arc<atomic::AtomicUsize>,
+    ) -> error::Result<(Self, WsSender, WsReceiver)> {
+        let (sender, receiver) = endpoint.connect().await?;
+        let task = Self::with_endpoint(endpoint, reconnect, keepalive, timeouts, id);
+        Ok((task, sender, receiver))
+    }
+
+    fn with_endpoint(
+        endpoint: Endpoint,
+        reconnect: Option<ReconnectPolicy>,
+        keepalive: Option<KeepAlive>,
+        timeouts: RequestTimeouts,
+        id: Arc<atomic::AtomicUsize>,
+    ) -> Self {
+        Self {
+            endpoint,
+            reconnect,
+            keepalive,
+            timeouts,
+            id,
+            pending: Default::default(),
+            deadlines: Default::default(),
+            request_deadlines: Default::default(),
+            subscriptions: Default::default(),
+            in_flight: Default::default(),
+            subscribe_requests: Default::default(),
+            restorable: Default::default(),
+            resubscribe_requests: Default::default(),
+            aliases: Default::default(),
+            retired: Default::default(),
+        }
+    }
+
+    async fn into_task<R>(
+        mut self,
+        sender: WsSender,
+        receiver: WsReceiver,
+        requests: R,
+        control: mpsc::UnboundedReceiver<TransportMessage>,
+    ) where
+        R: Stream<Item = TransportMessage> + Unpin,
+    {
+        let mut sender = sender;
+        let mut receiver = Box::pin(as_frame_stream(receiver).fuse());
+        let mut heartbeat = Heartbeat::new(self.keepalive.clone());
+        let (mut expiry, mut armed) = (Fuse::terminated(), None);
+        let requests = requests.fuse();
+        let control = control.fuse();
+        pin_mut!(requests);
+        pin_mut!(control);
+        'connection: loop {
+            let next_deadline = self.deadlines.keys().next().map(|&(deadline, _)| deadline);
+            if next_deadline != armed {
+                armed = next_deadline;
+                expiry = match next_deadline {
+                    Some(deadline) => Delay::new(deadline.saturating_duration_since(Instant::now())).fuse(),
+                    None => Fuse::terminated(),
+                };
+            }
+
+            let (mut received, mut beat, mut expired) = (false, false, false);
+            let mut error: Option<Disconnect> = select! {
+                msg = requests.next() => match msg {
+                    Some(msg) => self.on_transport_message(msg, &mut sender).await.map(Into::into),
+                    None => None,
+                },
+                msg = control.next() => match msg {
+                    Some(msg) => self.on_transport_message(msg, &mut sender).await.map(Into::into),
+                    None => None,
+                },
+                res = receiver.next() => match res {
+                    Some(Ok(frame)) => {
+                        received = true;
+                        if let Frame::Data(data) = frame {
+                            self.on_message(&data).await;
+                        }
+                        None
+                    },
+                    Some(Err(e)) => Some(e.into()),
+                    None => Some(connection::Error::Closed.into()),
+                },
+                _ = &mut heartbeat.timer => {
+                    beat = true;
+                    None
+                },
+                _ = &mut expiry => {
+                    expired = true;
+                    None
+                },
+                complete => break,
+            };
+            if expired {
+                self.on_timeout();
+                armed = None;
+            }
+            if received {
+                heartbeat.reset();
+            }
+            if beat {
+                error = Self::on_heartbeat(&mut heartbeat, &mut sender).await;
+            }
+
+            while let Some(e) = error.take() {
+                log::error!("WS connection error: {}", e);
+                self.on_connection_lost(e.error());
+                if requests.is_terminated() {
+                    break 'connection;
+                }
+                let (new_sender, new_receiver) = match self.reconnect().await {
+                    Some(connection) => connection,
+                    None => break 'connection,
+                };
+                sender = new_sender;
+                receiver = Box::pin(as_frame_stream(new_receiver).fuse());
+                heartbeat.reset();
+                for request in self.restore() {
+                    if let Err(e) = send_text(&mut sender, request).await {
+                        error = Some(e.into());
+                        break;
+                    }
+                }
+            }
+        }
+
+        for (_, pending) in std::mem::take(&mut self.pending) {
+            let _ = pending.send(Err(connection_lost()));
+        }
+    }
 
+    /// Sends a ping, or reports the connection dead if the previous one wasn't answered.
+    async fn on_heartbeat(heartbeat: &mut Heartbeat, sender: &mut WsSender) -> Option<Disconnect> {
+        let timeout = heartbeat.policy.as_ref()?.timeout;
+        if heartbeat.awaiting_pong {
+            return Some(Disconnect::PongTimeout(timeout));
+        }
+        heartbeat.awaiting_pong = true;
+        heartbeat.timer = Delay::new(timeout).fuse();
+        log::trace!("Sending ping.");
+        let ping = soketto::data::ByteSlice125::try_from(&[][..]).expect("Empty ping payload fits a control frame.");
+        if let Err(e) = sender.send_ping(ping).await {
+            return Some(e.into());
+        }
+        sender.flush().await.err().map(Into::into)
+    }
+
+    async fn on_transport_message(
+        &mut self,
+        msg: TransportMessage,
+        sender: &mut WsSender,
+    ) -> Option<connection::Error> {
+        match msg {
+            TransportMessage::Request {
+                id,
+                request,
+                sender: tx,
+            } => {
+                let request = self.on_request(id, request, tx);
+                send_text(sender, request).await.err()
+            }
+            TransportMessage::Subscribe { id, sink } => {
+                if self.subscriptions.insert(id.clone(), sink).is_some() {
+                    log::warn!("Replacing already-registered subscription with id {:?}", id);
+                }
+                None
+            }
+            TransportMessage::Unsubscribe { id } => {
+                if self.subscriptions.remove(&id).is_none() {
+                    log::warn!("Unsubscribing from non-existent subscription with id {:?}", id);
+                }
+                self.retire_subscription(&id);
+                None
+            }
+        }
+    }
+
+    /// Registers a request and returns it serialized.
+    fn on_request(&mut self, id: RequestId, mut request: rpc::Request, tx: Pending) -> String {
+        if self.reconnect.is_some() {
+            self.track_subscription_calls(id, &mut request);
+        }
+        if let Some(timeout) = self.timeouts.get(&request) {
+            let deadline = Instant::now() + timeout;
+            if let Some(previous) = self.request_deadlines.insert(id, deadline) {
+                self.deadlines.remove(&(previous, id));
+            }
+            self.deadlines.insert((deadline, id), timeout);
+        }
+        let request = helpers::to_string(&request);
+        log::debug!("[{}] Calling: {}", id, request);
+        if self.pending.insert(id, tx).is_some() {
+            log::warn!("Replacing a pending request with id {:?}", id);
+        }
+        if matches!(self.reconnect, Some(ref policy) if policy.retry_requests) {
+            self.in_flight.insert(id, request.clone());
+        }
+        request
+    }
+
+    /// Remembers parameters of `eth_subscribe` calls and rewrites `eth_unsubscribe` calls
+    /// of restored subscriptions to use the id assigned by the current server.
+    fn track_subscription_calls(&mut self, id: RequestId, request: &mut rpc::Request) {
+        let call = match request {
+            rpc::Request::Single(rpc::Call::MethodCall(ref mut call)) => call,
+            _ => return,
+        };
+        match call.method.as_str() {
+            "eth_subscribe" => {
+                self.subscribe_requests.insert(id, call.params.clone());
+            }
+            "eth_unsubscribe" => {
+                if let rpc::Params::Array(ref mut params) = call.params {
+                    if let Some(rpc::Value::String(ref mut subscription)) = params.get_mut(0) {
+                        let original = SubscriptionId::from(subscription.clone());
+                        if let Some(restorable) = self.restorable.get(&original) {
+                            *subscription = restorable.server_id.clone();
+                        } else if let Some(server_id) = self.retired.remove(&original) {
+                            *subscription = server_id;
+                        }
+                        self.forget_subscription(&original);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Stops restoring given subscription.
+    fn forget_subscription(&mut self, id: &SubscriptionId) -> Option<String> {
+        let restorable = self.restorable.remove(id)?;
+        self.aliases.remove(&SubscriptionId::from(restorable.server_id.clone()));
+        Some(restorable.server_id)
+    }
+
+    /// Stops restoring a subscription which is no longer consumed,
+    /// but keeps its server id in case `eth_unsubscribe` is called for it.
+    fn retire_subscription(&mut self, id: &SubscriptionId) {
+        if let Some(server_id) = self.forget_subscription(id) {
+            self.retired.insert(id.clone(), server_id);
+        }
+    }
+
+    async fn on_message(&mut self, data: &[u8]) {
+        let message = match parse_message(data) {
+            Some(Message::Notification(id, result)) => {
+                let id = self.aliases.get(&id).cloned().unwrap_or(id);
+                Message::Notification(id, result)
+            }
+            Some(Message::Response(id, result)) => {
+                self.in_flight.remove(&id);
+                self.clear_deadline(id);
+                if let Some(original) = self.resubscribe_requests.remove(&id) {
+                    match (subscription_id(&result), self.restorable.get_mut(&original)) {
+                        (Some(server_id), Some(restorable)) => {
+                            log::debug!("Restored subscription {:?} as {:?}", original, server_id);
+                            restorable.server_id = server_id.clone();
+                            self.aliases.insert(server_id.into(), original);
+                        }
+                        (_, None) => log::warn!("Restored subscription {:?} is no longer used", original),
+                        (None, _) => log::error!("Unable to restore subscription {:?}: {:?}", original, result),
+                    }
+                    return;
+                }
+                if let Some(params) = self.subscribe_requests.remove(&id) {
+                    if let Some(server_id) = subscription_id(&result) {
+                        self.restorable
+                            .insert(server_id.clone().into(), Restorable { params, server_id });
+                    }
+                }
+                Message::Response(id, result)
+            }
+            None => return,
+        };
+        dispatch_message(message, &self.subscriptions, &mut self.pending).await;
+    }
+
+    /// Fails requests which are past their deadline.
+    fn on_timeout(&mut self) {
+        let now = Instant::now();
+        while let Some((&(deadline, id), &timeout)) = self.deadlines.iter().next() {
+            if deadline > now {
+                break;
+            }
+            self.deadlines.remove(&(deadline, id));
+            self.request_deadlines.remove(&id);
+            if let Some(pending) = self.pending.remove(&id) {
+                log::debug!("[{}] Request timed out.", id);
+                self.in_flight.remove(&id);
+                self.subscribe_requests.remove(&id);
+                let _ = pending.send(Err(Error::Transport(format!("Request timed out after {:?}", timeout))));
+            }
+        }
+    }
+
+    /// Stops tracking the deadline of a request which is no longer pending.
+    fn clear_deadline(&mut self, id: RequestId) {
+        if let Some(deadline) = self.request_deadlines.remove(&id) {
+            self.deadlines.remove(&(deadline, id));
+        }
+    }
+
+    /// Fails requests which are not going to be re-sent with given error.
+    fn on_connection_lost(&mut self, error: Error) {
+        let retry = matches!(self.reconnect, Some(ref policy) if policy.retry_requests);
+        let retained = std::mem::take(&mut self.in_flight);
+        for (id, pending) in std::mem::take(&mut self.pending) {
+            if retry && retained.contains_key(&id) {
+                self.pending.insert(id, pending);
+            } else {
+                self.subscribe_requests.remove(&id);
+                self.clear_deadline(id);
+                let _ = pending.send(Err(error.clone()));
+            }
+        }
+        self.in_flight = retained;
+        // Responses to re-issued subscriptions are never going to arrive.
+        self.resubscribe_requests.clear();
+        self.aliases.clear();
+        self.retired.clear();
+    }
+
+    /// Re-dials the server according to the re-connection policy.
+    async fn reconnect(&self) -> Option<(WsSender, WsReceiver)> {
+        let policy = self.reconnect.as_ref()?;
+        let mut attempt = 0;
+        loop {
+            if matches!(policy.max_attempts, Some(max) if attempt >= max) {
+                log::error!(
+                    "Unable to re-connect to {} after {} attempts.",
+                    self.endpoint.url,
+                    attempt
+                );
+                return None;
+            }
+            Delay::new(policy.backoff(attempt)).await;
+            attempt += 1;
+            log::info!("Re-connecting to {} (attempt {})", self.endpoint.url, attempt);
+            match self.endpoint.connect().await {
+                Ok(connection) => return Some(connection),
+                Err(e) => log::warn!("Unable to re-connect: {:?}", e),
+            }
+        }
+    }
+
+    /// Returns requests to be sent after re-connecting: the retried ones and re-issued subscriptions.
+    fn restore(&mut self) -> Vec<String> {
+        let mut requests: Vec<String> = self.in_flight.values().cloned().collect();
+        for (original, restorable) in &self.restorable {
+            let id = self.id.fetch_add(1, atomic::Ordering::AcqRel);
+            let request = rpc::Request::Single(rpc::Call::MethodCall(rpc::MethodCall {
+                jsonrpc: Some(rpc::Version::V2),
+                method: "eth_subscribe".into(),
+                params: restorable.params.clone(),
+                id: rpc::Id::Num(id as u64),
+            }));
+            self.resubscribe_requests.insert(id, original.clone());
+            requests.push(helpers::to_string(&request));
+        }
+        requests
+    }
+}
+
+/// Result of the opening handshake.
+enum Handshake {
+    Accepted(WsSender, WsReceiver),
+    Redirect { status_code: u16, location: String },
+}
+
+impl Endpoint {
+    async fn connect(&self) -> error::Result<(WsSender, WsReceiver)> {
+        let mut url = self.url.clone();
+        let mut redirects = 0;
+        loop {
+            match self.handshake(&url).await? {
+                Handshake::Accepted(sender, receiver) => return Ok((sender, receiver)),
+                Handshake::Redirect { status_code, location } if redirects < self.max_redirects => {
+                    redirects += 1;
+                    url = url.join(&location)?;
+                    log::debug!("(code: {}) Redirected to {}", status_code, url);
+                }
+                Handshake::Redirect { status_code, location } => {
+                    return Err(error::Error::Transport(format!(
+                        "(code: {}) Too many redirects, last location: {}",
+                        status_code, location
+                    )))
+                }
+            }
+        }
+    }
+
+    async fn handshake(&self, url: &Url) -> error::Result<Handshake> {
         let scheme = match url.scheme() {
             s if s == "ws" || s == "wss" => s,
             s => return Err(error::Error::Transport(format!("Wrong scheme: {}", s))),
@@ -109,13 +707,25 @@ impl WsServerTask {
             None => return Err(error::Error::Transport("Wrong host name".to_string())),
         };
         let port = url.port().unwrap_or(if scheme == "ws" { 80 } else { 443 });
-        let addrs = format!("{}:{}", host, port);
 
-        let stream = compat::raw_tcp_stream(addrs).await?;
+        let stream = match self.proxy {
+            Some(ref proxy) if proxy.intercepts(host) => {
+                let mut stream = compat::raw_tcp_stream(proxy.address()).await?;
+                proxy::socks5_connect(&mut compat::compat(&mut stream), proxy, host, port).await?;
+                stream
+            }
+            _ => compat::raw_tcp_stream(format!("{}:{}", host, port)).await?,
+        };
         let socket = if scheme == "wss" {
             #[cfg(any(feature = "ws-tls-tokio", feature = "ws-tls-async-std"))]
             {
-                let stream = async_native_tls::connect(host, stream).await?;
+                let stream = match self.tls {
+                    Some(ref tls) => {
+                        let connector = async_native_tls::TlsConnector::from(tls.builder()?);
+                        connector.connect(tls.domain_override().unwrap_or(host), stream).await?
+                    }
+                    None => async_native_tls::connect(host, stream).await?,
+                };
                 MaybeTlsStream::Tls(compat::compat(stream))
             }
             #[cfg(not(any(feature = "ws-tls-tokio", feature = "ws-tls-async-std")))]
@@ -127,170 +737,106 @@ impl WsServerTask {
 
         let mut client = Client::new(socket, host, url.path());
         let handshake = client.handshake();
-        let (sender, receiver) = match handshake.await? {
-            ServerResponse::Accepted { .. } => client.into_builder().finish(),
-            ServerResponse::Redirect { status_code, location } => {
-                return Err(error::Error::Transport(format!(
-                    "(code: {}) Unable to follow redirects: {}",
-                    status_code, location
-                )))
+        match handshake.await? {
+            ServerResponse::Accepted { .. } => {
+                let (sender, receiver) = client.into_builder().finish();
+                Ok(Handshake::Accepted(sender, receiver))
             }
-            ServerResponse::Rejected { status_code } => {
-                return Err(error::Error::Transport(format!(
-                    "(code: {}) Connection rejected.",
-                    status_code
-                )))
-            }
-        };
-
-        Ok(Self {
-            pending: Default::default(),
-            subscriptions: Default::default(),
-            sender,
-            receiver,
-        })
+            ServerResponse::Redirect { status_code, location } => Ok(Handshake::Redirect { status_code, location }),
+            ServerResponse::Rejected { status_code } => Err(error::Error::Transport(format!(
+                "(code: {}) Connection rejected.",
+                status_code
+            ))),
+        }
     }
+}
 
-    async fn into_task(self, requests: mpsc::UnboundedReceiver<TransportMessage>) {
-        let Self {
-            receiver,
-            mut sender,
-            mut pending,
-            mut subscriptions,
-        } = self;
+async fn send_text(sender: &mut WsSender, request: String) -> Result<(), connection::Error> {
+    sender.send_text(request).await?;
+    sender.flush().await
+}
 
-        let receiver = as_data_stream(receiver).fuse();
-        let requests = requests.fuse();
-        pin_mut!(receiver);
-        pin_mut!(requests);
-        loop {
-            select! {
-                msg = requests.next() => match msg {
-                    Some(TransportMessage::Request { id, request, sender: tx }) => {
-                        if pending.insert(id.clone(), tx).is_some() {
-                            log::warn!("Replacing a pending request with id {:?}", id);
-                        }
-                        let res = sender.send_text(request).await;
-                        let res2 = sender.flush().await;
-                        if let Err(e) = res.and(res2) {
-                            // TODO [ToDr] Re-connect.
-                            log::error!("WS connection error: {:?}", e);
-                            pending.remove(&id);
-                        }
-                    }
-                    Some(TransportMessage::Subscribe { id, sink }) => {
-                        if subscriptions.insert(id.clone(), sink).is_some() {
-                            log::warn!("Replacing already-registered subscription with id {:?}", id);
-                        }
-                    }
-                    Some(TransportMessage::Unsubscribe { id }) => {
-                        if subscriptions.remove(&id).is_none() {
-                            log::warn!("Unsubscribing from non-existent subscription with id {:?}", id);
-                        }
-                    }
-                    None => {}
-                },
-                res = receiver.next() => match res {
-                    Some(Ok(data)) => {
-                        handle_message(&data, &subscriptions, &mut pending);
-                    },
-                    Some(Err(e)) => {
-                        log::error!("WS connection error: {:?}", e);
-                        break;
-                    },
-                    None => break,
-                },
-                complete => break,
-            }
-        }
+/// Extracts the subscription id from a response to `eth_subscribe`.
+fn subscription_id(result: &BatchResult) -> Option<String> {
+    match result {
+        Ok(outputs) => match outputs.first() {
+            Some(Ok(rpc::Value::String(id))) => Some(id.clone()),
+            _ => None,
+        },
+        Err(_) => None,
     }
 }
 
-fn as_data_stream<T: Unpin + futures::AsyncRead + futures::AsyncWrite>(
+fn connection_lost() -> Error {
+    Error::Transport("WS connection lost.".into())
+}
+
+/// A frame received from the server.
+enum Frame {
+    /// Text or binary message.
+    Data(Vec<u8>),
+    /// Answer to a ping.
+    Pong,
+}
+
+fn as_frame_stream<T: Unpin + futures::AsyncRead + futures::AsyncWrite>(
     receiver: soketto::connection::Receiver<T>,
-) -> impl Stream<Item = Result<Vec<u8>, soketto::connection::Error>> {
+) -> impl Stream<Item = Result<Frame, soketto::connection::Error>> {
     futures::stream::unfold(receiver, |mut receiver| async move {
         let mut data = Vec::new();
-        Some(match receiver.receive_data(&mut data).await {
-            Ok(_) => (Ok(data), receiver),
-            Err(e) => (Err(e), receiver),
-        })
+        let frame = match receiver.receive(&mut data).await {
+            Ok(soketto::Incoming::Data(_)) => Ok(Frame::Data(data)),
+            Ok(soketto::Incoming::Pong(_)) => Ok(Frame::Pong),
+            Err(e) => Err(e),
+        };
+        Some((frame, receiver))
     })
 }
 
-fn handle_message(
-    data: &[u8],
-    subscriptions: &BTreeMap<SubscriptionId, Subscription>,
-    pending: &mut BTreeMap<RequestId, Pending>,
-) {
-    log::trace!("Message received: {:?}", data);
-    if let Ok(notification) = helpers::to_notification_from_slice(data) {
-        if let rpc::Params::Map(params) = notification.params {
-            let id = params.get("subscription");
-            let result = params.get("result");
-
-            if let (Some(&rpc::Value::String(ref id)), Some(result)) = (id, result) {
-                let id: SubscriptionId = id.clone().into();
-                if let Some(stream) = subscriptions.get(&id) {
-                    if let Err(e) = stream.unbounded_send(result.clone()) {
-                        log::error!("Error sending notification: {:?} (id: {:?}", e, id);
-                    }
-                } else {
-                    log::warn!("Got notification for unknown subscription (id: {:?})", id);
-                }
-            } else {
-                log::error!("Got unsupported notification (id: {:?})", id);
-            }
-        }
-    } else {
-        let response = helpers::to_response_from_slice(data);
-        let outputs = match response {
-            Ok(rpc::Response::Single(output)) => vec![output],
-            Ok(rpc::Response::Batch(outputs)) => outputs,
-            _ => vec![],
-        };
+/// Behaviour of a bounded request queue when it's full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestOverflow {
+    /// The returned future waits until there is space in the queue.
+    Wait,
+    /// The request fails immediately with `Error::Transport`.
+    Fail,
+}
 
-        let id = match outputs.get(0) {
-            Some(&rpc::Output::Success(ref success)) => success.id.clone(),
-            Some(&rpc::Output::Failure(ref failure)) => failure.id.clone(),
-            None => rpc::Id::Num(0),
-        };
+/// Sending half of the request queue.
+#[derive(Clone)]
+enum RequestQueue {
+    Unbounded(mpsc::UnboundedSender<TransportMessage>),
+    Wait(Arc<futures::lock::Mutex<mpsc::Sender<TransportMessage>>>),
+    Fail(Arc<parking_lot::Mutex<mpsc::Sender<TransportMessage>>>),
+}
 
-        if let rpc::Id::Num(num) = id {
-            if let Some(request) = pending.remove(&(num as usize)) {
-                log::trace!("Responding to (id: {:?}) with {:?}", num, outputs);
-                if let Err(err) = request.send(helpers::to_results_from_outputs(outputs)) {
-                    log::warn!("Sending a response to deallocated channel: {:?}", err);
-                }
-            } else {
-                log::warn!("Got response for unknown request (id: {:?})", num);
-            }
-        } else {
-            log::warn!("Got unsupported response (id: {:?})", id);
+type RequestStream = Pin<Box<dyn Stream<Item = TransportMessage> + Send>>;
+
+fn request_queue(limit: Option<(usize, RequestOverflow)>) -> (RequestQueue, RequestStream) {
+    match limit {
+        None => {
+            let (sink, stream) = mpsc::unbounded();
+            (RequestQueue::Unbounded(sink), Box::pin(stream))
+        }
+        Some((capacity, overflow)) => {
+            // The sender is never cloned, so the channel holds at most `capacity` messages.
+            let (sink, stream) = mpsc::channel(capacity.saturating_sub(1));
+            let queue = match overflow {
+                RequestOverflow::Wait => RequestQueue::Wait(Arc::new(futures::lock::Mutex::new(sink))),
+                RequestOverflow::Fail => RequestQueue::Fail(Arc::new(parking_lot::Mutex::new(sink))),
+            };
+            (queue, Box::pin(stream))
         }
     }
 }
 
-enum TransportMessage {
-    Request {
-        id: RequestId,
-        request: String,
-        sender: oneshot::Sender<BatchResult>,
-    },
-    Subscribe {
-        id: SubscriptionId,
-        sink: mpsc::UnboundedSender<rpc::Value>,
-    },
-    Unsubscribe {
-        id: SubscriptionId,
-    },
-}
-
 /// WebSocket transport
 #[derive(Clone)]
 pub struct WebSocket {
     id: Arc<atomic::AtomicUsize>,
-    requests: mpsc::UnboundedSender<TransportMessage>,
+    requests: RequestQueue,
+    control: mpsc::UnboundedSender<TransportMessage>,
+    subscription_buffer: Option<(usize, NotificationOverflow)>,
 }
 
 impl fmt::Debug for WebSocket {
@@ -302,88 +848,176 @@ impl fmt::Debug for WebSocket {
 impl WebSocket {
     /// Create new WebSocket transport.
     pub async fn new(url: &str) -> error::Result<Self> {
-        let id = Arc::new(atomic::AtomicUsize::new(1));
-        let task = WsServerTask::new(url).await?;
-        // TODO [ToDr] Not unbounded?
-        let (sink, stream) = mpsc::unbounded();
-        // Spawn background task for the transport.
-        #[cfg(feature = "ws-tokio")]
-        tokio::spawn(task.into_task(stream));
-        #[cfg(feature = "ws-async-std")]
-        async_std::task::spawn(task.into_task(stream));
+        Self::builder(url).build().await
+    }
 
-        Ok(Self { id, requests: sink })
+    /// Create a builder of the WebSocket transport, allowing to customize the connection.
+    pub fn builder(url: &str) -> Builder {
+        Builder {
+            url: url.into(),
+            reconnect: None,
+            keepalive: None,
+            timeouts: Default::default(),
+            proxy: None,
+            max_redirects: 5,
+            request_queue: None,
+            subscription_buffer: None,
+            #[cfg(any(feature = "ws-tls-tokio", feature = "ws-tls-async-std"))]
+            tls: None,
+        }
     }
 
     fn send(&self, msg: TransportMessage) -> error::Result {
-        self.requests.unbounded_send(msg).map_err(dropped_err)
+        self.control.unbounded_send(msg).map_err(dropped_err)
     }
 
-    fn send_request(&self, id: RequestId, request: rpc::Request) -> error::Result<oneshot::Receiver<BatchResult>> {
-        let request = helpers::to_string(&request);
-        log::debug!("[{}] Calling: {}", id, request);
+    fn send_request<R, T>(&self, id: RequestId, request: rpc::Request, extract: T) -> Response<R, T> {
         let (sender, receiver) = oneshot::channel();
-        self.send(TransportMessage::Request { id, request, sender })?;
-        Ok(receiver)
+        let msg = TransportMessage::Request { id, request, sender };
+        match self.requests {
+            RequestQueue::Unbounded(ref requests) => {
+                let res = requests.unbounded_send(msg).map_err(dropped_err);
+                Response::new(res.map(|_| receiver), extract)
+            }
+            RequestQueue::Fail(ref requests) => {
+                let res = requests.lock().try_send(msg).map_err(|e| {
+                    if e.is_full() {
+                        Error::Transport("Request queue is full.".into())
+                    } else {
+                        dropped_err(e)
+                    }
+                });
+                Response::new(res.map(|_| receiver), extract)
+            }
+            RequestQueue::Wait(ref requests) => {
+                let requests = requests.clone();
+                let queued = async move {
+                    let mut requests = requests.lock().await;
+                    SinkExt::feed(&mut *requests, msg).await.map_err(dropped_err)?;
+                    Ok(receiver)
+                };
+                Response::queued(Box::pin(queued), extract)
+            }
+        }
     }
 }
 
-fn dropped_err<T>(_: T) -> error::Error {
-    Error::Transport("Cannot send request. Internal task finished.".into())
+/// WebSocket transport builder.
+#[derive(Debug, Clone)]
+pub struct Builder {
+    url: String,
+    reconnect: Option<ReconnectPolicy>,
+    keepalive: Option<KeepAlive>,
+    timeouts: RequestTimeouts,
+    proxy: Option<Proxy>,
+    max_redirects: usize,
+    request_queue: Option<(usize, RequestOverflow)>,
+    subscription_buffer: Option<(usize, NotificationOverflow)>,
+    #[cfg(any(feature = "ws-tls-tokio", feature = "ws-tls-async-std"))]
+    tls: Option<TlsConfig>,
 }
 
-fn batch_to_single(response: BatchResult) -> SingleResult {
-    match response?.into_iter().next() {
-        Some(res) => res,
-        None => Err(Error::InvalidResponse("Expected single, got batch.".into())),
+impl Builder {
+    /// Re-connect according to given policy when the connection is lost.
+    ///
+    /// By default the transport does not re-connect and fails all subsequent requests.
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
     }
-}
 
-fn batch_to_batch(res: BatchResult) -> BatchResult {
-    res
-}
+    /// Send pings to detect a dead connection according to given settings.
+    ///
+    /// By default no pings are sent.
+    pub fn keepalive(mut self, keepalive: KeepAlive) -> Self {
+        self.keepalive = Some(keepalive);
+        self
+    }
 
-enum ResponseState {
-    Receiver(Option<error::Result<oneshot::Receiver<BatchResult>>>),
-    Waiting(oneshot::Receiver<BatchResult>),
-}
+    /// Fail requests which don't receive a response within given time.
+    ///
+    /// By default requests wait for the response indefinitely.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.default = Some(timeout);
+        self
+    }
 
-/// A WS resonse wrapper.
-pub struct Response<R, T> {
-    extract: T,
-    state: ResponseState,
-    _data: std::marker::PhantomData<R>,
-}
+    /// Use a different timeout for calls of given method (e.g. a longer one for `trace_replayBlockTransactions`).
+    ///
+    /// Applies even if no `request_timeout` is set.
+    pub fn method_timeout(mut self, method: &str, timeout: Duration) -> Self {
+        self.timeouts.methods.insert(method.into(), timeout);
+        self
+    }
 
-impl<R, T> Response<R, T> {
-    fn new(response: error::Result<oneshot::Receiver<BatchResult>>, extract: T) -> Self {
-        Self {
-            extract,
-            state: ResponseState::Receiver(Some(response)),
-            _data: Default::default(),
-        }
+    /// Connect through given SOCKS5 proxy.
+    ///
+    /// Unlike the HTTP transport, proxy environment variables are not used.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
     }
-}
 
-impl<R, T> Future for Response<R, T>
-where
-    R: Unpin + 'static,
-    T: Fn(BatchResult) -> error::Result<R> + Unpin + 'static,
-{
-    type Output = error::Result<R>;
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-        loop {
-            match self.state {
-                ResponseState::Receiver(ref mut res) => {
-                    let receiver = res.take().expect("Receiver state is active only once; qed")?;
-                    self.state = ResponseState::Waiting(receiver)
-                }
-                ResponseState::Waiting(ref mut future) => {
-                    let response = ready!(future.poll_unpin(cx)).map_err(dropped_err)?;
-                    return Poll::Ready((self.extract)(response));
-                }
-            }
+    /// Follow at most given number of redirects during the opening handshake (5 by default).
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Limit the number of requests waiting to be sent to the server.
+    ///
+    /// By default the queue is unbounded.
+    pub fn request_queue(mut self, capacity: usize, overflow: RequestOverflow) -> Self {
+        self.request_queue = Some((capacity, overflow));
+        self
+    }
+
+    /// Limit the number of notifications buffered for every subscription.
+    ///
+    /// By default the buffers are unbounded.
+    pub fn subscription_buffer(mut self, capacity: usize, overflow: NotificationOverflow) -> Self {
+        self.subscription_buffer = Some((capacity, overflow));
+        self
+    }
+
+    /// Use custom TLS settings for `wss` connections.
+    #[cfg(any(feature = "ws-tls-tokio", feature = "ws-tls-async-std"))]
+    pub fn tls(mut self, config: TlsConfig) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    /// Connect to the server and spawn the background task of the transport.
+    pub async fn build(self) -> error::Result<WebSocket> {
+        if matches!(self.proxy, Some(ref proxy) if !proxy.is_socks()) {
+            return Err(Error::Transport(
+                "WebSocket transport supports only SOCKS5 proxies.".into(),
+            ));
         }
+        let endpoint = Endpoint {
+            url: Url::parse(&self.url)?,
+            proxy: self.proxy,
+            max_redirects: self.max_redirects,
+            #[cfg(any(feature = "ws-tls-tokio", feature = "ws-tls-async-std"))]
+            tls: self.tls,
+        };
+        let id = Arc::new(atomic::AtomicUsize::new(1));
+        let (task, sender, receiver) =
+            WsServerTask::new(endpoint, self.reconnect, self.keepalive, self.timeouts, id.clone()).await?;
+        let (control, control_stream) = mpsc::unbounded();
+        let (requests, requests_stream) = request_queue(self.request_queue);
+        // Spawn background task for the transport.
+        #[cfg(feature = "ws-tokio")]
+        tokio::spawn(task.into_task(sender, receiver, requests_stream, control_stream));
+        #[cfg(feature = "ws-async-std")]
+        async_std::task::spawn(task.into_task(sender, receiver, requests_stream, control_stream));
+
+        Ok(WebSocket {
+            id,
+            requests,
+            control,
+            subscription_buffer: self.subscription_buffer,
+        })
     }
 }
 
@@ -398,8 +1032,7 @@ impl Transport for WebSocket {
     }
 
     fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
-        let response = self.send_request(id, rpc::Request::Single(request));
-        Response::new(response, batch_to_single)
+        self.send_request(id, rpc::Request::Single(request), batch_to_single)
     }
 }
 
@@ -413,17 +1046,15 @@ impl BatchTransport for WebSocket {
         let mut it = requests.into_iter();
         let (id, first) = it.next().map(|x| (x.0, Some(x.1))).unwrap_or_else(|| (0, None));
         let requests = first.into_iter().chain(it.map(|x| x.1)).collect();
-        let response = self.send_request(id, rpc::Request::Batch(requests));
-        Response::new(response, batch_to_batch)
+        self.send_request(id, rpc::Request::Batch(requests), batch_to_batch)
     }
 }
 
 impl DuplexTransport for WebSocket {
-    type NotificationStream = mpsc::UnboundedReceiver<rpc::Value>;
+    type NotificationStream = NotificationStream;
 
     fn subscribe(&self, id: SubscriptionId) -> error::Result<Self::NotificationStream> {
-        // TODO [ToDr] Not unbounded?
-        let (sink, stream) = mpsc::unbounded();
+        let (sink, stream) = notification_channel(self.subscription_buffer);
         self.send(TransportMessage::Subscribe { id, sink })?;
         Ok(stream)
     }
@@ -600,4 +1231,509 @@ mod tests {
             }
         }
     }
+
+    type ServerStream = BufReader<BufWriter<compat::TcpStream>>;
+
+    async fn accept(
+        listener: &mut compat::TcpListener,
+    ) -> (connection::Sender<ServerStream>, connection::Receiver<ServerStream>) {
+        let (socket, _) = listener.accept().await.unwrap();
+        let socket = compat::compat(socket);
+        let mut server = handshake::Server::new(BufReader::new(BufWriter::new(socket)));
+        let key = server.receive_request().await.unwrap().into_key();
+        let accept = handshake::server::Response::Accept {
+            key: &key,
+            protocol: None,
+        };
+        server.send_response(&accept).await.unwrap();
+        server.into_builder().finish()
+    }
+
+    async fn expect(receiver: &mut connection::Receiver<ServerStream>, expected: &str) {
+        let mut data = Vec::new();
+        receiver.receive_data(&mut data).await.unwrap();
+        assert_eq!(std::str::from_utf8(&data), Ok(expected));
+    }
+
+    async fn reply(sender: &mut connection::Sender<ServerStream>, response: &str) {
+        sender.send_text(response).await.unwrap();
+        sender.flush().await.unwrap();
+    }
+
+    #[test]
+    fn should_limit_reconnect_backoff() {
+        let policy = ReconnectPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(1), Duration::from_secs(2));
+        assert_eq!(policy.backoff(2), Duration::from_secs(4));
+        assert_eq!(policy.backoff(3), Duration::from_secs(5));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn should_fail_pending_requests_when_connection_is_lost() {
+        let _ = env_logger::try_init();
+        // given
+        let mut listener = compat::TcpListener::bind("127.0.0.1:3002").await.unwrap();
+        tokio::spawn(async move {
+            let (mut sender, mut receiver) = accept(&mut listener).await;
+            expect(
+                &mut receiver,
+                r#"{"jsonrpc":"2.0","method":"eth_blockNumber","params":[],"id":1}"#,
+            )
+            .await;
+            sender.close().await.unwrap();
+        });
+        let ws = WebSocket::new("ws://127.0.0.1:3002").await.unwrap();
+
+        // when
+        let res = ws.execute("eth_blockNumber", vec![]).await;
+
+        // then
+        assert_eq!(res, Err(Error::Transport("WS connection lost.".into())));
+    }
+
+    #[tokio::test]
+    async fn should_restore_subscriptions_after_reconnecting() {
+        let _ = env_logger::try_init();
+        // given
+        let mut listener = compat::TcpListener::bind("127.0.0.1:3003").await.unwrap();
+        let server = tokio::spawn(async move {
+            let (mut sender, mut receiver) = accept(&mut listener).await;
+            expect(
+                &mut receiver,
+                r#"{"jsonrpc":"2.0","method":"eth_subscribe","params":["newHeads"],"id":1}"#,
+            )
+            .await;
+            reply(&mut sender, r#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#).await;
+            expect(
+                &mut receiver,
+                r#"{"jsonrpc":"2.0","method":"eth_blockNumber","params":[],"id":2}"#,
+            )
+            .await;
+            reply(&mut sender, r#"{"jsonrpc":"2.0","id":2,"result":"0x10"}"#).await;
+            reply(
+                &mut sender,
+                r#"{"jsonrpc":"2.0","method":"eth_subscription","params":{"subscription":"0x1","result":1}}"#,
+            )
+            .await;
+            sender.close().await.unwrap();
+            drop((sender, receiver));
+
+            let (mut sender, mut receiver) = accept(&mut listener).await;
+            expect(
+                &mut receiver,
+                r#"{"jsonrpc":"2.0","method":"eth_subscribe","params":["newHeads"],"id":3}"#,
+            )
+            .await;
+            reply(&mut sender, r#"{"jsonrpc":"2.0","id":3,"result":"0x2"}"#).await;
+            reply(
+                &mut sender,
+                r#"{"jsonrpc":"2.0","method":"eth_subscription","params":{"subscription":"0x2","result":2}}"#,
+            )
+            .await;
+            expect(
+                &mut receiver,
+                r#"{"jsonrpc":"2.0","method":"eth_unsubscribe","params":["0x2"],"id":4}"#,
+            )
+            .await;
+            reply(&mut sender, r#"{"jsonrpc":"2.0","id":4,"result":true}"#).await;
+        });
+        let ws = WebSocket::builder("ws://127.0.0.1:3003")
+            .reconnect(ReconnectPolicy {
+                initial_backoff: Duration::from_millis(10),
+                ..Default::default()
+            })
+            .build()
+            .await
+            .unwrap();
+        let id = ws.execute("eth_subscribe", vec!["newHeads".into()]).await;
+        assert_eq!(id, Ok("0x1".into()));
+        let mut stream = ws.subscribe(SubscriptionId::from("0x1".to_owned())).unwrap();
+        assert_eq!(ws.execute("eth_blockNumber", vec![]).await, Ok("0x10".into()));
+
+        // when
+        let first = stream.next().await;
+        let second = stream.next().await;
+        let unsubscribed = ws.execute("eth_unsubscribe", vec!["0x1".into()]).await;
+
+        // then
+        assert_eq!(first, Some(1.into()));
+        assert_eq!(second, Some(2.into()));
+        assert_eq!(unsubscribed, Ok(true.into()));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_fail_requests_when_pong_times_out() {
+        let _ = env_logger::try_init();
+        // given
+        let mut listener = compat::TcpListener::bind("127.0.0.1:3009").await.unwrap();
+        tokio::spawn(async move {
+            let (_sender, mut receiver) = accept(&mut listener).await;
+            expect(
+                &mut receiver,
+                r#"{"jsonrpc":"2.0","method":"eth_blockNumber","params":[],"id":1}"#,
+            )
+            .await;
+            // Hold the connection without answering pings.
+            tokio::time::delay_for(Duration::from_secs(5)).await;
+        });
+        let ws = WebSocket::builder("ws://127.0.0.1:3009")
+            .keepalive(KeepAlive {
+                interval: Duration::from_millis(50),
+                timeout: Duration::from_millis(50),
+            })
+            .build()
+            .await
+            .unwrap();
+        let mut stream = ws.subscribe(SubscriptionId::from("0x1".to_owned())).unwrap();
+
+        // when
+        let res = ws.execute("eth_blockNumber", vec![]).await;
+
+        // then
+        assert_eq!(
+            res,
+            Err(Error::Transport(
+                "WS connection timed out: no pong received within 50ms.".into()
+            ))
+        );
+        assert_eq!(stream.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn should_keep_connection_alive_while_pings_are_answered() {
+        let _ = env_logger::try_init();
+        // given
+        let mut listener = compat::TcpListener::bind("127.0.0.1:3010").await.unwrap();
+        tokio::spawn(async move {
+            let (mut sender, mut receiver) = accept(&mut listener).await;
+            expect(
+                &mut receiver,
+                r#"{"jsonrpc":"2.0","method":"eth_blockNumber","params":[],"id":1}"#,
+            )
+            .await;
+            // Pings are answered while receiving.
+            let mut data = Vec::new();
+            let idle = tokio::time::timeout(Duration::from_millis(300), receiver.receive_data(&mut data));
+            assert!(idle.await.is_err());
+            reply(&mut sender, r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#).await;
+        });
+        let ws = WebSocket::builder("ws://127.0.0.1:3010")
+            .keepalive(KeepAlive {
+                interval: Duration::from_millis(50),
+                timeout: Duration::from_millis(50),
+            })
+            .build()
+            .await
+            .unwrap();
+
+        // when
+        let res = ws.execute("eth_blockNumber", vec![]).await;
+
+        // then
+        assert_eq!(res, Ok("0x10".into()));
+    }
+
+    #[tokio::test]
+    async fn should_fail_requests_without_response_in_time() {
+        let _ = env_logger::try_init();
+        // given
+        let mut listener = compat::TcpListener::bind("127.0.0.1:3011").await.unwrap();
+        tokio::spawn(async move {
+            let (mut sender, mut receiver) = accept(&mut listener).await;
+            expect(
+                &mut receiver,
+                r#"{"jsonrpc":"2.0","method":"eth_blockNumber","params":[],"id":1}"#,
+            )
+            .await;
+            expect(
+                &mut receiver,
+                r#"{"jsonrpc":"2.0","method":"trace_replayBlockTransactions","params":[],"id":2}"#,
+            )
+            .await;
+            tokio::time::delay_for(Duration::from_millis(300)).await;
+            // The late response to timed out request is ignored.
+            reply(&mut sender, r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#).await;
+            reply(&mut sender, r#"{"jsonrpc":"2.0","id":2,"result":[]}"#).await;
+        });
+        let ws = WebSocket::builder("ws://127.0.0.1:3011")
+            .request_timeout(Duration::from_millis(100))
+            .method_timeout("trace_replayBlockTransactions", Duration::from_secs(5))
+            .build()
+            .await
+            .unwrap();
+
+        // when
+        let block = ws.execute("eth_blockNumber", vec![]);
+        let trace = ws.execute("trace_replayBlockTransactions", vec![]);
+        let (block, trace) = futures::join!(block, trace);
+
+        // then
+        assert_eq!(block, Err(Error::Transport("Request timed out after 100ms".into())));
+        assert_eq!(trace, Ok(rpc::Value::Array(vec![])));
+    }
+
+    #[tokio::test]
+    async fn should_follow_redirects() {
+        use futures::{AsyncReadExt, AsyncWriteExt};
+        let _ = env_logger::try_init();
+        // given
+        let mut gateway = compat::TcpListener::bind("127.0.0.1:3012").await.unwrap();
+        let mut listener = compat::TcpListener::bind("127.0.0.1:3013").await.unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = gateway.accept().await.unwrap();
+                let mut socket = compat::compat(socket);
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0u8];
+                    socket.read_exact(&mut byte).await.unwrap();
+                    request.push(byte[0]);
+                }
+                let response = "HTTP/1.1 301 Moved Permanently\r\nLocation: ws://127.0.0.1:3013/\r\n\r\n";
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        tokio::spawn(async move {
+            let (mut sender, mut receiver) = accept(&mut listener).await;
+            expect(
+                &mut receiver,
+                r#"{"jsonrpc":"2.0","method":"eth_blockNumber","params":[],"id":1}"#,
+            )
+            .await;
+            reply(&mut sender, r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#).await;
+        });
+
+        // when
+        let not_followed = WebSocket::builder("ws://127.0.0.1:3012").max_redirects(0).build().await;
+        let ws = WebSocket::new("ws://127.0.0.1:3012").await.unwrap();
+
+        // then
+        assert_eq!(
+            not_followed.unwrap_err(),
+            Error::Transport("(code: 301) Too many redirects, last location: ws://127.0.0.1:3013/".into())
+        );
+        assert_eq!(ws.execute("eth_blockNumber", vec![]).await, Ok("0x10".into()));
+    }
+
+    #[tokio::test]
+    async fn should_connect_through_socks5_proxy() {
+        use futures::{AsyncReadExt, AsyncWriteExt};
+        let _ = env_logger::try_init();
+        // given
+        let mut listener = compat::TcpListener::bind("127.0.0.1:3014").await.unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = compat::compat(socket);
+            let mut greeting = [0u8; 3];
+            socket.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [5, 1, 0]);
+            socket.write_all(&[5, 0]).await.unwrap();
+            let mut request = [0u8; 19];
+            socket.read_exact(&mut request).await.unwrap();
+            assert_eq!(&request[..5], &[5, 1, 0, 3, 12]);
+            assert_eq!(&request[5..17], b"node.example");
+            assert_eq!(&request[17..], &8545u16.to_be_bytes());
+            socket.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0x1f, 0x90]).await.unwrap();
+
+            // The proxy is connected to the node now.
+            let mut server = handshake::Server::new(BufReader::new(BufWriter::new(socket)));
+            let key = server.receive_request().await.unwrap().into_key();
+            let accept = handshake::server::Response::Accept {
+                key: &key,
+                protocol: None,
+            };
+            server.send_response(&accept).await.unwrap();
+            let (mut sender, mut receiver) = server.into_builder().finish();
+            expect(
+                &mut receiver,
+                r#"{"jsonrpc":"2.0","method":"eth_blockNumber","params":[],"id":1}"#,
+            )
+            .await;
+            reply(&mut sender, r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#).await;
+        });
+        let proxy = Proxy::new("socks5h://127.0.0.1:3014").unwrap().no_proxy("localhost");
+
+        // when
+        let http_proxy = WebSocket::builder("ws://node.example:8545")
+            .proxy(Proxy::new("http://127.0.0.1:3014").unwrap())
+            .build()
+            .await;
+        let ws = WebSocket::builder("ws://node.example:8545")
+            .proxy(proxy)
+            .build()
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(
+            http_proxy.unwrap_err(),
+            Error::Transport("WebSocket transport supports only SOCKS5 proxies.".into())
+        );
+        assert_eq!(ws.execute("eth_blockNumber", vec![]).await, Ok("0x10".into()));
+    }
+
+    fn queued_transport(capacity: usize, overflow: RequestOverflow) -> (WebSocket, RequestStream) {
+        let (requests, stream) = request_queue(Some((capacity, overflow)));
+        let (control, _) = mpsc::unbounded();
+        let ws = WebSocket {
+            id: Arc::new(atomic::AtomicUsize::new(1)),
+            requests,
+            control,
+            subscription_buffer: None,
+        };
+        (ws, stream)
+    }
+
+    #[tokio::test]
+    async fn should_fail_requests_when_queue_is_full() {
+        // given
+        let (ws, mut stream) = queued_transport(2, RequestOverflow::Fail);
+        let mut first = ws.execute("eth_blockNumber", vec![]);
+        let mut second = ws.execute("eth_blockNumber", vec![]);
+
+        // when
+        let third = ws.execute("eth_blockNumber", vec![]);
+
+        // then
+        assert_eq!(third.await, Err(Error::Transport("Request queue is full.".into())));
+        assert!(futures::poll!(&mut first).is_pending());
+        assert!(futures::poll!(&mut second).is_pending());
+        assert!(stream.next().await.is_some());
+        assert!(stream.next().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn should_wait_when_queue_is_full() {
+        // given
+        let (ws, mut stream) = queued_transport(1, RequestOverflow::Wait);
+        let mut first = ws.execute("eth_blockNumber", vec![]);
+        let mut second = ws.execute("eth_blockNumber", vec![]);
+        assert!(futures::poll!(&mut first).is_pending());
+        assert!(futures::poll!(&mut second).is_pending());
+
+        // when
+        let queued = match stream.next().await {
+            Some(TransportMessage::Request { id, sender, .. }) => {
+                sender.send(Ok(vec![Ok(rpc::Value::Null)])).unwrap();
+                id
+            }
+            _ => panic!("Expected request."),
+        };
+        assert!(futures::poll!(&mut second).is_pending());
+
+        // then
+        assert_eq!(first.await, Ok(rpc::Value::Null));
+        match stream.next().await {
+            Some(TransportMessage::Request { id, .. }) => assert_ne!(id, queued),
+            _ => panic!("Expected request."),
+        }
+    }
+
+    #[cfg(feature = "ws-tls-tokio")]
+    #[tokio::test]
+    async fn should_connect_with_custom_tls_config() {
+        let _ = env_logger::try_init();
+        // given
+        let identity = native_tls::Identity::from_pkcs12(include_bytes!("res/server.p12"), "web3").unwrap();
+        let acceptor = async_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+        let mut listener = compat::TcpListener::bind("127.0.0.1:3007").await.unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let socket = compat::compat(acceptor.accept(socket).await.unwrap());
+            let mut server = handshake::Server::new(BufReader::new(BufWriter::new(socket)));
+            let key = server.receive_request().await.unwrap().into_key();
+            let accept = handshake::server::Response::Accept {
+                key: &key,
+                protocol: None,
+            };
+            server.send_response(&accept).await.unwrap();
+            let (mut sender, mut receiver) = server.into_builder().finish();
+            let mut data = Vec::new();
+            receiver.receive_data(&mut data).await.unwrap();
+            assert_eq!(
+                std::str::from_utf8(&data),
+                Ok(r#"{"jsonrpc":"2.0","method":"eth_accounts","params":[],"id":1}"#)
+            );
+            sender
+                .send_text(r#"{"jsonrpc":"2.0","id":1,"result":"x"}"#)
+                .await
+                .unwrap();
+            sender.flush().await.unwrap();
+        });
+
+        // when
+        let ws = WebSocket::builder("wss://127.0.0.1:3007")
+            .tls(
+                TlsConfig::new()
+                    .add_root_certificate_pem(include_bytes!("res/ca.pem"))
+                    .domain("localhost"),
+            )
+            .build()
+            .await
+            .unwrap();
+
+        // then
+        assert_eq!(
+            ws.execute("eth_accounts", vec![]).await,
+            Ok(rpc::Value::String("x".into()))
+        );
+    }
+
+    #[cfg(feature = "ws-tls-tokio")]
+    #[tokio::test]
+    async fn should_reject_untrusted_server() {
+        let _ = env_logger::try_init();
+        // given
+        let identity = native_tls::Identity::from_pkcs12(include_bytes!("res/server.p12"), "web3").unwrap();
+        let acceptor = async_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+        let mut listener = compat::TcpListener::bind("127.0.0.1:3008").await.unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let _ = acceptor.accept(socket).await;
+        });
+
+        // when
+        let ws = WebSocket::builder("wss://127.0.0.1:3008")
+            .tls(TlsConfig::new().domain("localhost"))
+            .build()
+            .await;
+
+        // then
+        assert!(ws.is_err());
+    }
+
+    #[tokio::test]
+    async fn should_forget_deadlines_of_answered_requests() {
+        let endpoint = Endpoint {
+            url: "ws://127.0.0.1:8546".parse().unwrap(),
+            proxy: None,
+            max_redirects: 0,
+            #[cfg(any(feature = "ws-tls-tokio", feature = "ws-tls-async-std"))]
+            tls: None,
+        };
+        let timeouts = RequestTimeouts {
+            default: Some(Duration::from_secs(3600)),
+            ..Default::default()
+        };
+        let mut task = WsServerTask::with_endpoint(endpoint, None, None, timeouts, Default::default());
+
+        let (tx, rx) = futures::channel::oneshot::channel();
+        let request = helpers::build_request(1, "eth_blockNumber", vec![]);
+        task.on_request(1, rpc::Request::Single(request), tx);
+        assert_eq!(task.deadlines.len(), 1);
+
+        task.on_message(br#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#).await;
+
+        assert!(task.deadlines.is_empty());
+        assert!(task.request_deadlines.is_empty());
+        assert!(rx.await.unwrap().is_ok());
+    }
 }
@@ -0,0 +1,55 @@
+use crate::types::{U256, U64};
+use serde::{Deserialize, Serialize};
+
+/// The fee history of a range of blocks, as returned by `eth_feeHistory`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeHistory {
+    /// Lowest block number of the returned range.
+    pub oldest_block: U64,
+    /// Base fee per gas of each block in the range, followed by the base fee
+    /// of the next block after the newest one.
+    pub base_fee_per_gas: Vec<U256>,
+    /// Ratio of gas used to the gas limit of each block in the range.
+    pub gas_used_ratio: Vec<f64>,
+    /// Effective priority fees per gas at the requested percentiles for each
+    /// block in the range (None when no percentiles were requested).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward: Option<Vec<Vec<U256>>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FeeHistory;
+    use serde_json;
+
+    #[test]
+    fn should_deserialize_fee_history() {
+        let history_str = r#"{
+        "oldestBlock": "0xc72773",
+        "baseFeePerGas": ["0x7a3ac3a1d", "0x7b82e2d43", "0x7a6a88c0e"],
+        "gasUsedRatio": [0.5271004, 0.4501283],
+        "reward": [["0x3b9aca00", "0x59682f00"], ["0x3b9aca00", "0x77359400"]]
+    }"#;
+
+        let history: FeeHistory = serde_json::from_str(history_str).unwrap();
+
+        assert_eq!(history.oldest_block, 0xc72773.into());
+        assert_eq!(history.base_fee_per_gas.len(), 3);
+        assert_eq!(history.gas_used_ratio, vec![0.5271004, 0.4501283]);
+        assert_eq!(history.reward.unwrap()[1][1], 2_000_000_000u64.into());
+    }
+
+    #[test]
+    fn should_deserialize_fee_history_without_rewards() {
+        let history_str = r#"{
+        "oldestBlock": "0x1",
+        "baseFeePerGas": ["0x1", "0x2"],
+        "gasUsedRatio": [0.0]
+    }"#;
+
+        let history: FeeHistory = serde_json::from_str(history_str).unwrap();
+
+        assert_eq!(history.reward, None);
+    }
+}
@@ -3,6 +3,7 @@
 mod block;
 mod bytes;
 mod bytes_array;
+mod fee_history;
 mod log;
 mod parity_peers;
 mod recovery;
@@ -20,12 +21,13 @@ mod work;
 pub use self::block::{Block, BlockHeader, BlockId, BlockNumber};
 pub use self::bytes::Bytes;
 pub use self::bytes_array::BytesArray;
+pub use self::fee_history::FeeHistory;
 pub use self::log::{Filter, FilterBuilder, Log};
 pub use self::parity_peers::{
     EthProtocolInfo, ParityPeerInfo, ParityPeerType, PeerNetworkInfo, PeerProtocolsInfo, PipProtocolInfo,
 };
 pub use self::recovery::{Recovery, RecoveryMessage};
-pub use self::signed::{SignedData, SignedTransaction, TransactionParameters};
+pub use self::signed::{DecodedTransaction, SignedData, SignedTransaction, TransactionParameters};
 pub use self::sync_state::{SyncInfo, SyncState};
 pub use self::trace_filtering::{
     Action, ActionType, Call, CallResult, CallType, Create, CreateResult, Res, Reward, RewardType, Suicide, Trace,
@@ -35,7 +37,7 @@ pub use self::traces::{
     AccountDiff, BlockTrace, ChangedType, Diff, MemoryDiff, StateDiff, StorageDiff, TraceType, TransactionTrace,
     VMExecutedOperation, VMOperation, VMTrace,
 };
-pub use self::transaction::{RawTransaction, Receipt as TransactionReceipt, Transaction};
+pub use self::transaction::{AccessList, AccessListItem, RawTransaction, Receipt as TransactionReceipt, Transaction};
 pub use self::transaction_id::TransactionId;
 pub use self::transaction_request::{CallRequest, TransactionCondition, TransactionRequest};
 pub use self::txpool::{TxpoolContentInfo, TxpoolInspectInfo, TxpoolStatus};
@@ -6,7 +6,8 @@ use std::fmt::{Display, Formatter, Result as FmtResult};
 ///
 /// Note that the signature data is in 'Electrum' notation and may have chain
 /// replay protection applied. That means that `v` is expected to be `27`, `28`,
-/// or `35 + chain_id * 2` or `36 + chain_id * 2`.
+/// or `35 + chain_id * 2` or `36 + chain_id * 2`. Typed transactions use the
+/// standard `0` or `1` instead, which is also accepted.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Recovery {
     /// The message to recover
@@ -62,6 +63,7 @@ impl Recovery {
     /// (equivalent of returning `4` in some implementaions).
     pub fn recovery_id(&self) -> Option<i32> {
         match self.v {
+            0 | 1 => Some(self.v as _),
             27 => Some(0),
             28 => Some(1),
             v if v >= 35 => Some(((v - 1) % 2) as _),
@@ -1,4 +1,4 @@
-use crate::types::{Address, Bytes, CallRequest, H256, U256};
+use crate::types::{AccessList, Address, Bytes, CallRequest, H256, U256, U64};
 use serde::{Deserialize, Serialize};
 
 /// Struct representing signed data returned from `Accounts::sign` method.
@@ -27,6 +27,14 @@ pub struct SignedData {
 /// gas price will be used for `gas_price`, and the current network ID will be
 /// used for the `chain_id`.
 ///
+/// The transaction is signed as a typed EIP-2718 envelope when
+/// `transaction_type` is set, or when it can be inferred from the other fields:
+/// setting either of the EIP-1559 fee fields produces a type 2 transaction and
+/// setting only an `access_list` produces a type 1 (EIP-2930) transaction.
+/// For type 2 transactions a missing `max_fee_per_gas` defaults to the
+/// estimated gas price and a missing `max_priority_fee_per_gas` defaults to
+/// `max_fee_per_gas`.
+///
 /// It is worth noting that the chain ID is not equivalent to the network ID.
 /// They happen to be the same much of the time but it is recommended to set
 /// this for signing transactions.
@@ -45,7 +53,8 @@ pub struct TransactionParameters {
     pub to: Option<Address>,
     /// Supplied gas
     pub gas: U256,
-    /// Gas price (None for estimated gas price)
+    /// Gas price (None for estimated gas price), also the default max fee per
+    /// gas of type 2 transactions
     pub gas_price: Option<U256>,
     /// Transferred value
     pub value: U256,
@@ -53,6 +62,15 @@ pub struct TransactionParameters {
     pub data: Bytes,
     /// The chain ID (None for network ID)
     pub chain_id: Option<u64>,
+    /// Transaction type (None to infer it from the other fields)
+    pub transaction_type: Option<U64>,
+    /// Access list (None for no access list)
+    pub access_list: Option<AccessList>,
+    /// Max fee per gas (None for `gas_price`, or the estimated gas price if
+    /// that is None too, on type 2 transactions)
+    pub max_fee_per_gas: Option<U256>,
+    /// Max priority fee per gas (None for `max_fee_per_gas` on type 2 transactions)
+    pub max_priority_fee_per_gas: Option<U256>,
 }
 
 /// The default fas for transactions.
@@ -73,6 +91,10 @@ impl Default for TransactionParameters {
             value: U256::zero(),
             data: Bytes::default(),
             chain_id: None,
+            transaction_type: None,
+            access_list: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
         }
     }
 }
@@ -87,6 +109,10 @@ impl From<CallRequest> for TransactionParameters {
             value: call.value.unwrap_or_default(),
             data: call.data.unwrap_or_default(),
             chain_id: None,
+            transaction_type: call.transaction_type,
+            access_list: call.access_list,
+            max_fee_per_gas: call.max_fee_per_gas,
+            max_priority_fee_per_gas: call.max_priority_fee_per_gas,
         }
     }
 }
@@ -100,6 +126,10 @@ impl Into<CallRequest> for TransactionParameters {
             gas_price: self.gas_price,
             value: Some(self.value),
             data: Some(self.data),
+            transaction_type: self.transaction_type,
+            access_list: self.access_list,
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
         }
     }
 }
@@ -109,7 +139,8 @@ impl Into<CallRequest> for TransactionParameters {
 pub struct SignedTransaction {
     /// The given message hash
     pub message_hash: H256,
-    /// V value with chain replay protection.
+    /// V value with chain replay protection for legacy transactions, or the
+    /// signature's y-parity (`0` or `1`) for typed transactions.
     pub v: u64,
     /// R value.
     pub r: H256,
@@ -121,6 +152,29 @@ pub struct SignedTransaction {
     pub transaction_hash: H256,
 }
 
+/// Raw signed transaction decoded with `Accounts::decode_transaction`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedTransaction {
+    /// The signed transaction parameters.
+    ///
+    /// The `transaction_type`, `nonce` and the fee fields used by the
+    /// transaction type are always set. The `chain_id` is set unless this is a
+    /// legacy transaction without chain replay protection.
+    pub transaction: TransactionParameters,
+    /// The address recovered from the signature.
+    pub from: Address,
+    /// The hash of the signed message.
+    pub message_hash: H256,
+    /// V value as encoded in the transaction.
+    pub v: u64,
+    /// R value.
+    pub r: H256,
+    /// S value.
+    pub s: H256,
+    /// The transaction hash for the raw transaction.
+    pub transaction_hash: H256,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,4 +1,4 @@
-use crate::types::{Bytes, Index, Log, H160, H2048, H256, U256, U64};
+use crate::types::{Address, Bytes, Index, Log, H160, H2048, H256, U256, U64};
 use serde::{Deserialize, Serialize};
 
 /// Description of a Transaction, pending or in the chain.
@@ -33,6 +33,18 @@ pub struct Transaction {
     /// Raw transaction data
     #[serde(default)]
     pub raw: Option<Bytes>,
+    /// Transaction type, `None` for legacy transactions.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub transaction_type: Option<U64>,
+    /// Access list (EIP-2930 and EIP-1559 transactions only).
+    #[serde(rename = "accessList", default, skip_serializing_if = "Option::is_none")]
+    pub access_list: Option<AccessList>,
+    /// Max fee per gas (EIP-1559 transactions only).
+    #[serde(rename = "maxFeePerGas", default, skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<U256>,
+    /// Max priority fee per gas (EIP-1559 transactions only).
+    #[serde(rename = "maxPriorityFeePerGas", default, skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<U256>,
 }
 
 /// "Receipt" of an executed transaction: details of its execution.
@@ -70,8 +82,27 @@ pub struct Receipt {
     /// Logs bloom
     #[serde(rename = "logsBloom")]
     pub logs_bloom: H2048,
+    /// Transaction type, `None` for legacy transactions and pre-Berlin nodes.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub transaction_type: Option<U64>,
+    /// Price actually paid per unit of gas, `None` for pre-London nodes.
+    #[serde(rename = "effectiveGasPrice", default, skip_serializing_if = "Option::is_none")]
+    pub effective_gas_price: Option<U256>,
 }
 
+/// Access list entry (EIP-2930): an address and the storage slots it pre-warms.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessListItem {
+    /// Accessed address
+    pub address: Address,
+    /// Accessed storage keys
+    #[serde(rename = "storageKeys")]
+    pub storage_keys: Vec<H256>,
+}
+
+/// Access list (EIP-2930)
+pub type AccessList = Vec<AccessListItem>;
+
 /// Raw bytes of a signed, but not yet sent transaction
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RawTransaction {
@@ -122,6 +153,7 @@ pub struct RawTransactionDetails {
 mod tests {
     use super::RawTransaction;
     use super::Receipt;
+    use super::Transaction;
     use serde_json;
 
     #[test]
@@ -169,6 +201,64 @@ mod tests {
         let _receipt: Receipt = serde_json::from_str(receipt_str).unwrap();
     }
 
+    #[test]
+    fn should_deserialize_receipt_with_type() {
+        let receipt_str = r#"{
+        "blockHash": "0x83eaba432089a0bfe99e9fc9022d1cfcb78f95f407821be81737c84ae0b439c5",
+        "blockNumber": "0x38",
+        "contractAddress": null,
+        "cumulativeGasUsed": "0x927c0",
+        "effectiveGasPrice": "0x4e3b29200",
+        "gasUsed": "0x5208",
+        "logs": [],
+        "logsBloom": "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        "transactionHash": "0x422fb0d5953c0c48cbb42fb58e1c30f5e150441c68374d70ca7d4f191fd56f26",
+        "transactionIndex": "0x0",
+        "status": "0x1",
+        "type": "0x2"
+    }"#;
+
+        let receipt: Receipt = serde_json::from_str(receipt_str).unwrap();
+
+        assert_eq!(receipt.transaction_type, Some(2.into()));
+        assert_eq!(receipt.effective_gas_price, Some(21_000_000_000u64.into()));
+    }
+
+    #[test]
+    fn should_deserialize_eip1559_transaction() {
+        let tx_str = r#"{
+        "accessList": [
+          {
+            "address": "0xde0b295669a9fd93d5f28d9ec85e40f4cb697bae",
+            "storageKeys": ["0x0000000000000000000000000000000000000000000000000000000000000001"]
+          }
+        ],
+        "blockHash": null,
+        "blockNumber": null,
+        "from": "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23",
+        "gas": "0x1e8480",
+        "gasPrice": "0x4e3b29200",
+        "hash": "0x6c047dd805d967fd21e5a811425b2509216254833ea823aaa05c43a188ff24d2",
+        "input": "0x01020304",
+        "maxFeePerGas": "0x4e3b29200",
+        "maxPriorityFeePerGas": "0x77359400",
+        "nonce": "0x1",
+        "to": "0xf0109fc8df283027b6285cc889f5aa624eac1f55",
+        "transactionIndex": null,
+        "type": "0x2",
+        "value": "0x3b9aca00"
+    }"#;
+
+        let tx: Transaction = serde_json::from_str(tx_str).unwrap();
+
+        assert_eq!(tx.transaction_type, Some(2.into()));
+        assert_eq!(tx.max_fee_per_gas, Some(21_000_000_000u64.into()));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(2_000_000_000u64.into()));
+        let access_list = tx.access_list.unwrap();
+        assert_eq!(access_list.len(), 1);
+        assert_eq!(access_list[0].storage_keys, vec![super::H256::from_low_u64_be(1)]);
+    }
+
     #[test]
     fn test_deserialize_signed_tx_parity() {
         // taken from RPC docs.
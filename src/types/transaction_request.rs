@@ -1,4 +1,4 @@
-use crate::types::{Address, Bytes, U256};
+use crate::types::{AccessList, Address, Bytes, U256, U64};
 use serde::{Deserialize, Serialize};
 
 /// Call contract request (eth_call / eth_estimateGas)
@@ -27,6 +27,18 @@ pub struct CallRequest {
     /// Data (None for empty data)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<Bytes>,
+    /// Transaction type (None for legacy, or inferred by the node)
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub transaction_type: Option<U64>,
+    /// Access list (None for no access list)
+    #[serde(rename = "accessList", skip_serializing_if = "Option::is_none")]
+    pub access_list: Option<AccessList>,
+    /// Max fee per gas (None for legacy or sensible default)
+    #[serde(rename = "maxFeePerGas", skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<U256>,
+    /// Max priority fee per gas (None for legacy or sensible default)
+    #[serde(rename = "maxPriorityFeePerGas", skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<U256>,
 }
 
 /// Send Transaction Parameters
@@ -56,6 +68,18 @@ pub struct TransactionRequest {
     /// Min block inclusion (None for include immediately)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub condition: Option<TransactionCondition>,
+    /// Transaction type (None for legacy, or inferred by the node)
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub transaction_type: Option<U64>,
+    /// Access list (None for no access list)
+    #[serde(rename = "accessList", skip_serializing_if = "Option::is_none")]
+    pub access_list: Option<AccessList>,
+    /// Max fee per gas (None for legacy or sensible default)
+    #[serde(rename = "maxFeePerGas", skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<U256>,
+    /// Max priority fee per gas (None for legacy or sensible default)
+    #[serde(rename = "maxPriorityFeePerGas", skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<U256>,
 }
 
 /// Represents condition on minimum block number or block timestamp.
@@ -73,6 +97,7 @@ pub enum TransactionCondition {
 #[cfg(test)]
 mod tests {
     use super::{Address, CallRequest, TransactionCondition, TransactionRequest};
+    use crate::types::AccessListItem;
     use serde_json;
 
     #[test]
@@ -85,6 +110,7 @@ mod tests {
             gas_price: None,
             value: Some(5_000_000.into()),
             data: Some(vec![1, 2, 3].into()),
+            ..Default::default()
         };
 
         // when
@@ -132,6 +158,7 @@ mod tests {
             data: Some(vec![1, 2, 3].into()),
             nonce: None,
             condition: Some(TransactionCondition::Block(5)),
+            ..Default::default()
         };
 
         // when
@@ -174,4 +201,46 @@ mod tests {
         assert_eq!(deserialized.nonce, None);
         assert_eq!(deserialized.condition, Some(TransactionCondition::Block(5)));
     }
+
+    #[test]
+    fn should_serialize_eip1559_transaction_request() {
+        // given
+        let tx_request = TransactionRequest {
+            from: Address::from_low_u64_be(5),
+            to: Some(Address::from_low_u64_be(6)),
+            transaction_type: Some(2.into()),
+            access_list: Some(vec![AccessListItem {
+                address: Address::from_low_u64_be(7),
+                storage_keys: vec![],
+            }]),
+            max_fee_per_gas: Some(21_000_000_000u64.into()),
+            max_priority_fee_per_gas: Some(2_000_000_000u64.into()),
+            ..Default::default()
+        };
+
+        // when
+        let serialized = serde_json::to_string_pretty(&tx_request).unwrap();
+
+        // then
+        assert_eq!(
+            serialized,
+            r#"{
+  "from": "0x0000000000000000000000000000000000000005",
+  "to": "0x0000000000000000000000000000000000000006",
+  "type": "0x2",
+  "accessList": [
+    {
+      "address": "0x0000000000000000000000000000000000000007",
+      "storageKeys": []
+    }
+  ],
+  "maxFeePerGas": "0x4e3b29200",
+  "maxPriorityFeePerGas": "0x77359400"
+}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<TransactionRequest>(&serialized).unwrap(),
+            tx_request
+        );
+    }
 }